@@ -1,12 +1,18 @@
 use core::fmt;
-use std::str::FromStr;
+use std::{
+    io::{IsTerminal, Write},
+    str::FromStr,
+};
 
-use clap::Parser;
+use clap::{Args, Parser};
 use enum_dispatch::enum_dispatch;
 
-use crate::CmdExector;
+use crate::{
+    get_content, get_writer, process_compress_reader, process_data_uri_decode,
+    process_data_uri_encode, process_guess_mime, CmdExector,
+};
 
-use super::verify_file;
+use super::{parse_compression, print_decoded, verify_file, Compression};
 
 #[derive(Debug, Parser)]
 #[enum_dispatch(CmdExector)]
@@ -16,6 +22,9 @@ pub enum Base64SubCommand {
 
     #[command(name = "decode", about = "Decode a base64 string")]
     Decode(Base64DecodeOpts),
+
+    #[command(about = "Build a data: URI from a file, or parse one with --decode")]
+    DataUri(Base64DataUriOpts),
 }
 
 #[derive(Debug, Parser)]
@@ -25,6 +34,39 @@ pub struct Base64EncodeOpts {
 
     #[arg(long, value_parser = parse_base64_format, default_value = "standard")]
     pub format: Base64Format,
+
+    /// Line length of the output, 76 for MIME, 64 for PEM, 0 for no wrapping
+    #[arg(long, default_value_t = 0)]
+    pub wrap: usize,
+
+    #[command(flatten)]
+    pub padding: PaddingOpts,
+
+    /// Compress with gzip, zlib, zstd or brotli before encoding
+    #[arg(long, value_parser = parse_compression)]
+    pub compress: Option<Compression>,
+}
+
+/// Padding defaults to on for standard and off for url safe when encoding, and to either when decoding
+#[derive(Debug, Args)]
+pub struct PaddingOpts {
+    /// Pad the output / require padded input
+    #[arg(long, conflicts_with = "no_pad")]
+    pub pad: bool,
+
+    /// Don't pad the output / require unpadded input
+    #[arg(long)]
+    pub no_pad: bool,
+}
+
+impl PaddingOpts {
+    pub fn padding(&self) -> Option<bool> {
+        match (self.pad, self.no_pad) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Parser)]
@@ -34,6 +76,35 @@ pub struct Base64DecodeOpts {
 
     #[arg(long, value_parser = parse_base64_format, default_value = "standard")]
     pub format: Base64Format,
+
+    /// Write the decoded bytes to this file, `-` is stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+
+    #[command(flatten)]
+    pub padding: PaddingOpts,
+
+    /// Keep gzip, zlib and zstd payloads compressed instead of decompressing them
+    #[arg(long)]
+    pub no_decompress: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct Base64DataUriOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    /// Parse a data URI and write its payload instead of building one
+    #[arg(short, long)]
+    pub decode: bool,
+
+    /// Media type of the URI instead of the sniffed one, with --decode the URI must have this type
+    #[arg(long)]
+    pub mime: Option<String>,
+
+    /// With --decode, write the payload to this file, `-` is stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -45,8 +116,13 @@ pub enum Base64Format {
 impl CmdExector for Base64EncodeOpts {
     async fn execute(self) -> anyhow::Result<()> {
         let mut reader = crate::get_reader(&self.input)?;
-        let ret = crate::process_encode(&mut reader, self.format)?;
-        println!("{}", ret);
+        if let Some(compression) = self.compress {
+            reader = process_compress_reader(reader, compression, None)?;
+        }
+        let codec = self.format.codec(self.padding.padding(), self.wrap);
+        let mut stdout = std::io::stdout().lock();
+        crate::process_encode(&mut reader, &mut stdout, codec.as_ref())?;
+        writeln!(stdout)?;
         Ok(())
     }
 }
@@ -54,8 +130,63 @@ impl CmdExector for Base64EncodeOpts {
 impl CmdExector for Base64DecodeOpts {
     async fn execute(self) -> anyhow::Result<()> {
         let mut reader = crate::get_reader(&self.input)?;
-        let ret = crate::process_decode(&mut reader, self.format)?;
-        println!("{}", ret);
+        let decompress = !self.no_decompress;
+        let codec = self.format.codec(self.padding.padding(), 0);
+
+        let compression = if self.output == "-" && std::io::stdout().is_terminal() {
+            // 输出到终端的数据不会太大，先解码到内存里再决定怎么显示
+            let mut ret = Vec::new();
+            let compression =
+                crate::process_decode(&mut reader, &mut ret, codec.as_ref(), decompress)?;
+            print_decoded(&ret);
+            compression
+        } else {
+            let mut writer = get_writer(&self.output)?;
+            crate::process_decode(&mut reader, &mut writer, codec.as_ref(), decompress)?
+        };
+        if let Some(compression) = compression {
+            eprintln!("Decompressed {} data", compression);
+        }
+        Ok(())
+    }
+}
+
+impl CmdExector for Base64DataUriOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let content = get_content(&self.input)?;
+        if !self.decode {
+            let mime = match self.mime {
+                Some(mime) => mime,
+                None => {
+                    let path = (self.input != "-").then_some(self.input.as_str());
+                    process_guess_mime(&content, path)
+                }
+            };
+            println!("{}", process_data_uri_encode(&content, &mime)?);
+            return Ok(());
+        }
+
+        let uri = process_data_uri_decode(std::str::from_utf8(&content)?)?;
+        if let Some(expected) = &self.mime {
+            if !uri.mime.essence_str().eq_ignore_ascii_case(expected) {
+                anyhow::bail!("Expected media type {}, got {}", expected, uri.mime);
+            }
+        }
+        eprintln!("Media type: {}", uri.mime);
+        // 声明的类型和内容对不上时只提醒，不报错
+        if let Some(kind) = infer::get(&uri.data) {
+            if kind.mime_type() != uri.mime.essence_str() {
+                eprintln!("Warning: payload looks like {}", kind.mime_type());
+            }
+        }
+
+        if self.output == "-" && std::io::stdout().is_terminal() {
+            print_decoded(&uri.data);
+        } else {
+            let mut writer = get_writer(&self.output)?;
+            writer.write_all(&uri.data)?;
+            writer.flush()?;
+        }
         Ok(())
     }
 }
@@ -0,0 +1,177 @@
+use std::{
+    fmt,
+    io::{IsTerminal, Write},
+    str::FromStr,
+};
+
+use clap::Parser;
+
+use crate::{
+    get_content, get_reader, get_writer, process_codec_decode, process_codec_encode,
+    process_detect_encoding, process_segwit_decode, CmdExector,
+};
+
+use super::{print_decoded, verify_file};
+
+#[derive(Debug, Parser)]
+pub struct EncodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    /// base64, base64url, hex, hex-upper, base32, base32-crockford, base58, base58check, ascii85, z85, bech32, bech32m or quoted-printable
+    #[arg(short, long, value_parser = parse_encoding)]
+    pub encoding: Encoding,
+
+    /// Human readable part of bech32, e.g. bc
+    #[arg(long)]
+    pub hrp: Option<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct DecodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    #[arg(short, long, value_parser = parse_encoding, required_unless_present = "auto")]
+    pub encoding: Option<Encoding>,
+
+    /// Guess the encoding (including JWT) and report what the decoded data looks like
+    #[arg(long, conflicts_with = "encoding")]
+    pub auto: bool,
+
+    /// Expected human readable part of bech32
+    #[arg(long)]
+    pub hrp: Option<String>,
+
+    /// Write the decoded bytes to this file, `-` is stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Encoding {
+    Base64,
+    Base64Url,
+    Hex,
+    HexUpper,
+    Base32,
+    Base32Crockford,
+    Base58,
+    Base58Check,
+    Ascii85,
+    Z85,
+    Bech32,
+    Bech32m,
+    QuotedPrintable,
+}
+
+impl CmdExector for EncodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let mut reader = get_reader(&self.input)?;
+        let codec = self.encoding.codec(self.hrp);
+        let ret = process_codec_encode(&mut reader, codec.as_ref())?;
+        println!("{}", ret);
+        Ok(())
+    }
+}
+
+impl CmdExector for DecodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let ret = match self.encoding {
+            Some(encoding @ (Encoding::Bech32 | Encoding::Bech32m)) => {
+                let text = get_content(&self.input)?;
+                if let Some((version, _)) = process_segwit_decode(&String::from_utf8_lossy(&text))?
+                {
+                    eprintln!("Segwit witness version {}", version);
+                }
+                let codec = encoding.codec(self.hrp);
+                process_codec_decode(&mut text.as_slice(), codec.as_ref())?
+            }
+            Some(encoding) => {
+                let mut reader = get_reader(&self.input)?;
+                let codec = encoding.codec(self.hrp);
+                process_codec_decode(&mut reader, codec.as_ref())?
+            }
+            None => {
+                let text = String::from_utf8(get_content(&self.input)?)?;
+                let mut candidates = process_detect_encoding(&text);
+                if candidates.is_empty() {
+                    anyhow::bail!("Input doesn't match any supported encoding");
+                }
+                // 报告写到 stderr，stdout 只留解码结果，方便接管道
+                for candidate in candidates.iter().take(5) {
+                    eprintln!(
+                        "{:>6.1}%  {:<16} {} ({} bytes)",
+                        candidate.confidence * 100.0,
+                        candidate.name,
+                        candidate.content,
+                        candidate.decoded.len()
+                    );
+                }
+                candidates.swap_remove(0).decoded
+            }
+        };
+
+        if self.output == "-" && std::io::stdout().is_terminal() {
+            print_decoded(&ret);
+        } else {
+            let mut writer = get_writer(&self.output)?;
+            writer.write_all(&ret)?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_encoding(encoding: &str) -> Result<Encoding, anyhow::Error> {
+    encoding.parse()
+}
+
+impl FromStr for Encoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "base64" => Ok(Encoding::Base64),
+            "base64url" => Ok(Encoding::Base64Url),
+            "hex" => Ok(Encoding::Hex),
+            "hex-upper" => Ok(Encoding::HexUpper),
+            "base32" => Ok(Encoding::Base32),
+            "base32-crockford" => Ok(Encoding::Base32Crockford),
+            "base58" => Ok(Encoding::Base58),
+            "base58check" => Ok(Encoding::Base58Check),
+            "ascii85" => Ok(Encoding::Ascii85),
+            "z85" => Ok(Encoding::Z85),
+            "bech32" => Ok(Encoding::Bech32),
+            "bech32m" => Ok(Encoding::Bech32m),
+            "quoted-printable" | "qp" => Ok(Encoding::QuotedPrintable),
+            _ => Err(anyhow::anyhow!("Invalid encoding")),
+        }
+    }
+}
+
+impl From<Encoding> for &'static str {
+    fn from(value: Encoding) -> Self {
+        match value {
+            Encoding::Base64 => "base64",
+            Encoding::Base64Url => "base64url",
+            Encoding::Hex => "hex",
+            Encoding::HexUpper => "hex-upper",
+            Encoding::Base32 => "base32",
+            Encoding::Base32Crockford => "base32-crockford",
+            Encoding::Base58 => "base58",
+            Encoding::Base58Check => "base58check",
+            Encoding::Ascii85 => "ascii85",
+            Encoding::Z85 => "z85",
+            Encoding::Bech32 => "bech32",
+            Encoding::Bech32m => "bech32m",
+            Encoding::QuotedPrintable => "quoted-printable",
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
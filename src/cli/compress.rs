@@ -0,0 +1,129 @@
+use std::{
+    fmt,
+    io::{self, IsTerminal, Read, Write},
+    str::FromStr,
+};
+
+use clap::Parser;
+
+use crate::{
+    get_reader, get_writer, process_compress_reader, process_decompress_auto,
+    process_decompress_reader, CmdExector,
+};
+
+use super::{print_decoded, verify_file};
+
+#[derive(Debug, Parser)]
+pub struct CompressOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    /// gzip, zlib, zstd or brotli
+    #[arg(short, long, value_parser = parse_compression, default_value = "gzip")]
+    pub format: Compression,
+
+    /// 0-9 for gzip/zlib, 1-22 for zstd, 0-11 for brotli
+    #[arg(long)]
+    pub level: Option<u32>,
+
+    /// Write the compressed bytes to this file, `-` is stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct DecompressOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    /// Detected from the magic bytes when omitted, brotli must be given explicitly
+    #[arg(short, long, value_parser = parse_compression)]
+    pub format: Option<Compression>,
+
+    /// Write the decompressed bytes to this file, `-` is stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zlib,
+    Zstd,
+    Brotli,
+}
+
+impl CmdExector for CompressOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        if self.output == "-" && io::stdout().is_terminal() {
+            anyhow::bail!("Refusing to write compressed data to a terminal, use -o or a pipe");
+        }
+        let reader = get_reader(&self.input)?;
+        let mut compressed = process_compress_reader(reader, self.format, self.level)?;
+        let mut writer = get_writer(&self.output)?;
+        io::copy(&mut compressed, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl CmdExector for DecompressOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let reader = get_reader(&self.input)?;
+        let mut decompressed = match self.format {
+            Some(format) => process_decompress_reader(reader, format)?,
+            None => match process_decompress_auto(reader)? {
+                (Some(_), decompressed) => decompressed,
+                (None, _) => anyhow::bail!(
+                    "Unknown compression format, use --format (brotli can't be detected)"
+                ),
+            },
+        };
+
+        if self.output == "-" && io::stdout().is_terminal() {
+            let mut ret = Vec::new();
+            decompressed.read_to_end(&mut ret)?;
+            print_decoded(&ret);
+        } else {
+            let mut writer = get_writer(&self.output)?;
+            io::copy(&mut decompressed, &mut writer)?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn parse_compression(format: &str) -> Result<Compression, anyhow::Error> {
+    format.parse()
+}
+
+impl FromStr for Compression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "gzip" | "gz" => Ok(Compression::Gzip),
+            "zlib" => Ok(Compression::Zlib),
+            "zstd" | "zst" => Ok(Compression::Zstd),
+            "brotli" | "br" => Ok(Compression::Brotli),
+            _ => Err(anyhow::anyhow!("Invalid compression format")),
+        }
+    }
+}
+
+impl From<Compression> for &'static str {
+    fn from(value: Compression) -> Self {
+        match value {
+            Compression::Gzip => "gzip",
+            Compression::Zlib => "zlib",
+            Compression::Zstd => "zstd",
+            Compression::Brotli => "brotli",
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
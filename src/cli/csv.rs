@@ -1,6 +1,7 @@
 use std::{fmt, str::FromStr};
 
 use clap::Parser;
+use enum_dispatch::enum_dispatch;
 
 use crate::CmdExector;
 
@@ -8,14 +9,19 @@ use super::verify_file;
 
 #[derive(Debug, Clone, Copy)]
 pub enum OutputFormat {
+    Csv,
     Json,
     Yaml,
 }
 
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CsvOpts {
-    #[arg(short, long, value_parser = verify_file)]
-    pub input: String,
+    #[command(subcommand)]
+    pub cmd: Option<CsvSubCommand>,
+
+    #[arg(short, long, value_parser = verify_file, required = true)]
+    pub input: Option<String>,
 
     #[arg(short, long)]
     pub output: Option<String>,
@@ -30,9 +36,37 @@ pub struct CsvOpts {
     pub header: bool,
 }
 
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExector)]
+pub enum CsvSubCommand {
+    #[command(about = "Generate fake CSV data from a column spec")]
+    Fake(CsvFakeOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvFakeOpts {
+    #[arg(short, long, value_parser = verify_file)]
+    pub spec: String,
+
+    #[arg(short, long, default_value_t = 100)]
+    pub rows: usize,
+
+    /// Seed for reproducible output, overrides the seed in the spec
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+
+    /// csv, json or yaml
+    #[arg(long, value_parser = parse_format, default_value = "csv")]
+    pub format: OutputFormat,
+}
+
 impl From<OutputFormat> for &'static str {
     fn from(value: OutputFormat) -> Self {
         match value {
+            OutputFormat::Csv => "csv",
             OutputFormat::Json => "json",
             OutputFormat::Yaml => "yaml",
         }
@@ -44,6 +78,7 @@ impl FromStr for OutputFormat {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
+            "csv" => Ok(OutputFormat::Csv),
             "json" => Ok(OutputFormat::Json),
             "yaml" => Ok(OutputFormat::Yaml),
             _ => Err(anyhow::anyhow!("Invalid format")),
@@ -59,12 +94,23 @@ impl fmt::Display for OutputFormat {
 
 impl CmdExector for CsvOpts {
     async fn execute(self) -> anyhow::Result<()> {
+        if let Some(cmd) = self.cmd {
+            return cmd.execute().await;
+        }
+
+        let input = self.input.expect("input is required without subcommand");
         let output = if let Some(output) = self.output {
             output.clone()
         } else {
             format!("output.{}", self.format)
         };
-        crate::process_csv(&self.input, output, self.format)
+        crate::process_csv(&input, output, self.format)
+    }
+}
+
+impl CmdExector for CsvFakeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        crate::process_csv_fake(&self.spec, self.rows, self.seed, &self.output, self.format)
     }
 }
 
@@ -1,39 +1,603 @@
-use clap::Parser;
+use std::{fmt, io::IsTerminal, str::FromStr};
 
-use crate::CmdExector;
-use zxcvbn::zxcvbn;
+use clap::{ArgAction, Args, Parser};
+use enum_dispatch::enum_dispatch;
+use serde::Serialize;
+
+use crate::{
+    get_content, get_reader, get_secret, process_genpass_batch, process_genpass_derive,
+    process_genpass_min_score, process_genpass_not_breached, process_genpass_policy,
+    process_password_audit, process_password_strength, process_token_generate,
+    process_token_verify, Argon2Params, BreachCorpus, CmdExector, GenPassOptions, PasswordPolicy,
+    StrengthReport,
+};
+
+use super::{verify_file, Argon2Opts};
 
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct GenPassOpts {
+    #[command(subcommand)]
+    pub cmd: Option<GenPassSubCommand>,
+
+    #[command(flatten)]
+    pub chars: CharClassOpts,
+
+    /// Generate a passphrase with this many words instead of random characters
+    #[arg(long)]
+    pub words: Option<u8>,
+
+    #[arg(long, default_value = "-")]
+    pub separator: String,
+
+    /// Capitalize the first letter of every word
+    #[arg(long)]
+    pub capitalize: bool,
+
+    /// Append a random digit to the passphrase
+    #[arg(long)]
+    pub append_digit: bool,
+
+    /// Append a random symbol to the passphrase
+    #[arg(long)]
+    pub append_symbol: bool,
+
+    /// Wordlist file with one word per line, EFF dice lists are also accepted
+    #[arg(long, value_parser = verify_file)]
+    pub wordlist: Option<String>,
+
+    /// Regenerate until the zxcvbn score (0-4) is at least this value
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=4))]
+    pub min_score: Option<u8>,
+
+    /// Print the password and its strength report as JSON, same as `--format json`
+    #[arg(long, conflicts_with = "format")]
+    pub json: bool,
+
+    /// Number of unique passwords to generate
+    #[arg(short, long)]
+    pub count: Option<usize>,
+
+    /// File with one identifier per line, each one is paired with a generated password
+    #[arg(long, value_parser = verify_file)]
+    pub names: Option<String>,
+
+    #[arg(long, value_parser = parse_genpass_format, default_value = "plain")]
+    pub format: GenPassFormat,
+
+    /// Toml policy file, only passwords complying with it are generated
+    #[arg(long, value_parser = verify_file)]
+    pub policy: Option<String>,
+
+    /// Local HIBP SHA-1 ordered dump, passwords found in it are regenerated
+    #[arg(long, value_parser = verify_file)]
+    pub check_breached: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GenPassFormat {
+    Plain,
+    Json,
+    Csv,
+}
+
+/// Length and character class options shared by random and derived passwords
+#[derive(Debug, Args)]
+pub struct CharClassOpts {
     #[arg(short, long, default_value_t = 16)]
     pub length: u8,
 
-    #[arg(long, default_value_t = true)]
+    #[arg(long = "no-uppercase", action = ArgAction::SetFalse)]
     pub uppercase: bool,
 
-    #[arg(long, default_value_t = true)]
+    #[arg(long = "no-lowercase", action = ArgAction::SetFalse)]
     pub lowercase: bool,
 
-    #[arg(long, default_value_t = true)]
+    #[arg(long = "no-number", action = ArgAction::SetFalse)]
     pub number: bool,
 
-    #[arg(long, default_value_t = true)]
+    #[arg(long = "no-symbol", action = ArgAction::SetFalse)]
     pub symbol: bool,
+
+    // 旧版本的 `--uppercase` 等参数，本来就是默认值，保留下来让旧的命令还能解析，写在后面的会覆盖 `--no-*`
+    #[arg(long = "uppercase", hide = true, overrides_with = "uppercase")]
+    pub with_uppercase: bool,
+
+    #[arg(long = "lowercase", hide = true, overrides_with = "lowercase")]
+    pub with_lowercase: bool,
+
+    #[arg(long = "number", hide = true, overrides_with = "number")]
+    pub with_number: bool,
+
+    #[arg(long = "symbol", hide = true, overrides_with = "symbol")]
+    pub with_symbol: bool,
+
+    #[arg(long)]
+    pub min_upper: Option<u8>,
+
+    #[arg(long)]
+    pub min_lower: Option<u8>,
+
+    #[arg(long)]
+    pub min_digits: Option<u8>,
+
+    #[arg(long)]
+    pub min_symbols: Option<u8>,
+
+    /// Custom alphabet used instead of the built-in character classes
+    #[arg(long)]
+    pub charset: Option<String>,
+
+    /// Characters that must not appear in the password
+    #[arg(long, default_value = "")]
+    pub exclude: String,
+
+    /// Include look-alike characters such as 0/O and l/I
+    #[arg(long)]
+    pub ambiguous: bool,
+}
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExector)]
+pub enum GenPassSubCommand {
+    #[command(about = "Audit the strength of existing passwords, one per line")]
+    Audit(GenPassAuditOpts),
+
+    #[command(about = "Check whether a password complies with a policy file")]
+    Check(GenPassCheckOpts),
+
+    #[command(about = "Derive a site specific password from a master passphrase")]
+    Derive(GenPassDeriveOpts),
+
+    #[command(about = "Generate or verify an API token with a checksum")]
+    Token(GenPassTokenOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct GenPassAuditOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    /// Context that makes a password weaker, e.g. usernames or company names
+    #[arg(short, long = "user-input")]
+    pub user_inputs: Vec<String>,
+
+    /// Exit with an error if any password scores below this value
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=4))]
+    pub fail_below: Option<u8>,
+
+    /// Local HIBP SHA-1 ordered dump, reports how often each password was breached
+    #[arg(long, value_parser = verify_file)]
+    pub check_breached: Option<String>,
+
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct GenPassCheckOpts {
+    /// File whose first line is the password, prompts without echo if omitted on a terminal
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    #[arg(long, value_parser = verify_file)]
+    pub policy: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct GenPassDeriveOpts {
+    #[arg(long)]
+    pub site: String,
+
+    #[arg(long)]
+    pub login: String,
+
+    /// Bump it to rotate the password of a site
+    #[arg(long, default_value_t = 1)]
+    pub counter: u32,
+
+    /// File holding the master passphrase, prompts without echo if omitted
+    #[arg(long, value_parser = verify_file)]
+    pub master_file: Option<String>,
+
+    #[command(flatten)]
+    pub argon2: Argon2Opts,
+
+    #[command(flatten)]
+    pub chars: CharClassOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct GenPassTokenOpts {
+    #[arg(long, default_value = "")]
+    pub prefix: String,
+
+    /// Number of random bytes in the token
+    #[arg(long, default_value_t = 32)]
+    pub bytes: usize,
+
+    #[arg(long, value_parser = parse_token_encoding, default_value = "base62")]
+    pub encoding: TokenEncoding,
+
+    /// Verify the checksum of this token instead of generating one
+    #[arg(long)]
+    pub verify: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TokenEncoding {
+    Base62,
+    Hex,
+    Base64Url,
+}
+
+#[derive(Debug, Serialize)]
+struct GenPassReport<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    password: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entropy: Option<f64>,
+    #[serde(flatten)]
+    strength: StrengthReport,
 }
 
 impl CmdExector for GenPassOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let ret = crate::process_genpass(
-            self.length,
-            self.uppercase,
-            self.lowercase,
-            self.number,
-            self.symbol,
-        )?;
-        println!("{}", ret);
+        if let Some(cmd) = self.cmd {
+            return cmd.execute().await;
+        }
+
+        let policy = match &self.policy {
+            Some(path) => Some(PasswordPolicy::load(path)?),
+            None => None,
+        };
+        if policy.is_some() && self.words.is_some() {
+            anyhow::bail!("--policy can't be used with --words");
+        }
+
+        let mut corpus = match &self.check_breached {
+            Some(path) => Some(BreachCorpus::open(path)?),
+            None => None,
+        };
+
+        let mut entropy = None;
+        let mut candidate = || -> anyhow::Result<String> {
+            if let Some(words) = self.words {
+                let (ret, bits) = crate::process_passphrase(
+                    words,
+                    &self.separator,
+                    self.capitalize,
+                    self.append_digit,
+                    self.append_symbol,
+                    self.wordlist.as_deref(),
+                )?;
+                entropy = Some(bits);
+                return Ok(ret);
+            }
+
+            let opts = GenPassOptions::from(&self.chars);
+            match &policy {
+                Some(policy) => process_genpass_policy(&opts, policy),
+                None => crate::process_genpass_with(&opts),
+            }
+        };
+        let mut generate = || match corpus.as_mut() {
+            Some(corpus) => process_genpass_not_breached(corpus, &mut candidate),
+            None => candidate(),
+        };
+
+        let names = match &self.names {
+            Some(path) => Some(read_names(path)?),
+            None => None,
+        };
+        let count = match (&names, self.count) {
+            (Some(names), Some(count)) if names.len() != count => {
+                anyhow::bail!("--count {} doesn't match {} names", count, names.len())
+            }
+            (Some(names), _) => names.len(),
+            (None, count) => count.unwrap_or(1),
+        };
+
+        let passwords = process_genpass_batch(count, || match self.min_score {
+            Some(min_score) => process_genpass_min_score(min_score, &mut generate),
+            None => {
+                let password = generate()?;
+                let strength = process_password_strength(&password, &[])?;
+                Ok((password, strength))
+            }
+        })?;
+
+        let format = if self.json {
+            GenPassFormat::Json
+        } else {
+            self.format
+        };
+        let reports: Vec<GenPassReport> = passwords
+            .into_iter()
+            .enumerate()
+            .map(|(i, (password, strength))| GenPassReport {
+                name: names.as_ref().map(|names| names[i].as_str()),
+                password,
+                entropy,
+                strength,
+            })
+            .collect();
+        // 只生成一个密码时保持原来的输出，批量时输出数组/多行
+        let batch = self.count.is_some() || names.is_some();
+
+        match format {
+            GenPassFormat::Plain => {
+                for report in &reports {
+                    match report.name {
+                        Some(name) => println!("{}\t{}", name, report.password),
+                        None => println!("{}", report.password),
+                    }
+                }
+                if !batch {
+                    eprintln!("Estimated strength: {}", reports[0].strength.score);
+                    if let Some(entropy) = entropy {
+                        eprintln!("Entropy: {:.1} bits", entropy);
+                    }
+                }
+            }
+            GenPassFormat::Json if batch => {
+                println!("{}", serde_json::to_string_pretty(&reports)?)
+            }
+            GenPassFormat::Json => println!("{}", serde_json::to_string_pretty(&reports[0])?),
+            GenPassFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(std::io::stdout());
+                match names {
+                    Some(_) => writer.write_record(["name", "password", "score"])?,
+                    None => writer.write_record(["password", "score"])?,
+                }
+                for report in &reports {
+                    let score = report.strength.score.to_string();
+                    match report.name {
+                        Some(name) => writer.write_record([name, &report.password, &score])?,
+                        None => writer.write_record([report.password.as_str(), &score])?,
+                    }
+                }
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CmdExector for GenPassAuditOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let user_inputs: Vec<&str> = self.user_inputs.iter().map(|s| s.as_str()).collect();
+        let mut corpus = match &self.check_breached {
+            Some(path) => Some(BreachCorpus::open(path)?),
+            None => None,
+        };
+        let report = if self.input == "-" && std::io::stdin().is_terminal() {
+            // 交互式输入时不回显密码，空行结束
+            let mut passwords = String::new();
+            loop {
+                let password = rpassword::prompt_password("Password (empty line to finish): ")?;
+                if password.is_empty() {
+                    break;
+                }
+                passwords.push_str(&password);
+                passwords.push('\n');
+            }
+            process_password_audit(&mut passwords.as_bytes(), &user_inputs, corpus.as_mut())?
+        } else {
+            let mut reader = get_reader(&self.input)?;
+            process_password_audit(&mut reader, &user_inputs, corpus.as_mut())?
+        };
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            for entry in &report.entries {
+                let strength = &entry.strength;
+                println!(
+                    "line {}: score {}, offline fast hashing crack time: {}",
+                    entry.line,
+                    strength.score,
+                    strength
+                        .crack_times
+                        .offline_fast_hashing_1e10_per_second
+                        .display
+                );
+                match entry.breached {
+                    Some(0) | None => {}
+                    Some(count) => println!("  breached: seen {} times in the corpus", count),
+                }
+                if let Some(warning) = &strength.warning {
+                    println!("  warning: {}", warning);
+                }
+                for suggestion in &strength.suggestions {
+                    println!("  suggestion: {}", suggestion);
+                }
+            }
 
-        let estimate = zxcvbn(&ret, &[])?;
-        eprintln!("Estimated strength: {}", estimate.score());
+            println!("\nAudited {} password(s)", report.entries.len());
+            for (score, count) in report.histogram.iter().enumerate() {
+                println!(
+                    "  score {}: {:>5} {}",
+                    score,
+                    count,
+                    "#".repeat((*count).min(50))
+                );
+            }
+            if corpus.is_some() {
+                println!("  breached: {:>5}", report.count_breached());
+            }
+        }
+
+        if let Some(fail_below) = self.fail_below {
+            let weak = report.count_below(fail_below);
+            if weak > 0 {
+                anyhow::bail!("{} password(s) scored below {}", weak, fail_below);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CmdExector for GenPassCheckOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let policy = PasswordPolicy::load(&self.policy)?;
+        let password = if self.input == "-" && std::io::stdin().is_terminal() {
+            rpassword::prompt_password("Password: ")?
+        } else {
+            let content = String::from_utf8(get_content(&self.input)?)?;
+            content.lines().next().unwrap_or_default().to_string()
+        };
+
+        let violations = policy.check(&password);
+        if violations.is_empty() {
+            println!("✓ Password complies with the policy");
+            return Ok(());
+        }
+
+        for violation in &violations {
+            println!("⚠ {}", violation);
+        }
+        anyhow::bail!(
+            "Password violates {} rule(s) of the policy",
+            violations.len()
+        )
+    }
+}
+
+impl CmdExector for GenPassDeriveOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let master = match &self.master_file {
+            Some(path) => get_secret(path)?,
+            None => rpassword::prompt_password("Master passphrase: ")?.into_bytes(),
+        };
+        if master.is_empty() {
+            anyhow::bail!("Master passphrase can't be empty");
+        }
+
+        let params = Argon2Params::from(&self.argon2);
+        let password = process_genpass_derive(
+            &master,
+            &self.site,
+            &self.login,
+            self.counter,
+            &GenPassOptions::from(&self.chars),
+            params,
+        )?;
+        println!("{}", password);
         Ok(())
     }
 }
+
+impl CmdExector for GenPassTokenOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let Some(token) = self.verify else {
+            let token = process_token_generate(&self.prefix, self.bytes, self.encoding)?;
+            println!("{}", token);
+            return Ok(());
+        };
+
+        let prefix = (!self.prefix.is_empty()).then_some(self.prefix.as_str());
+        if process_token_verify(&token, prefix, self.encoding)? {
+            println!("✓ Token checksum verified");
+            Ok(())
+        } else {
+            anyhow::bail!("⚠ Token checksum not verified")
+        }
+    }
+}
+
+impl From<&CharClassOpts> for GenPassOptions {
+    fn from(value: &CharClassOpts) -> Self {
+        Self {
+            length: value.length,
+            upper: value.uppercase,
+            lower: value.lowercase,
+            number: value.number,
+            symbol: value.symbol,
+            min_upper: value.min_upper,
+            min_lower: value.min_lower,
+            min_number: value.min_digits,
+            min_symbol: value.min_symbols,
+            charset: value.charset.clone(),
+            exclude: value.exclude.clone(),
+            ambiguous: value.ambiguous,
+        }
+    }
+}
+
+fn read_names(path: &str) -> anyhow::Result<Vec<String>> {
+    let content = String::from_utf8(get_content(path)?)?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+fn parse_genpass_format(format: &str) -> Result<GenPassFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl FromStr for GenPassFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "plain" => Ok(GenPassFormat::Plain),
+            "json" => Ok(GenPassFormat::Json),
+            "csv" => Ok(GenPassFormat::Csv),
+            _ => Err(anyhow::anyhow!("Invalid format")),
+        }
+    }
+}
+
+impl From<GenPassFormat> for &'static str {
+    fn from(value: GenPassFormat) -> Self {
+        match value {
+            GenPassFormat::Plain => "plain",
+            GenPassFormat::Json => "json",
+            GenPassFormat::Csv => "csv",
+        }
+    }
+}
+
+impl fmt::Display for GenPassFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+fn parse_token_encoding(encoding: &str) -> Result<TokenEncoding, anyhow::Error> {
+    encoding.parse()
+}
+
+impl FromStr for TokenEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "base62" => Ok(TokenEncoding::Base62),
+            "hex" => Ok(TokenEncoding::Hex),
+            "base64url" => Ok(TokenEncoding::Base64Url),
+            _ => Err(anyhow::anyhow!("Invalid encoding")),
+        }
+    }
+}
+
+impl From<TokenEncoding> for &'static str {
+    fn from(value: TokenEncoding) -> Self {
+        match value {
+            TokenEncoding::Base62 => "base62",
+            TokenEncoding::Hex => "hex",
+            TokenEncoding::Base64Url => "base64url",
+        }
+    }
+}
+
+impl fmt::Display for TokenEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
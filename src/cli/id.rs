@@ -0,0 +1,172 @@
+use std::{fmt, str::FromStr};
+
+use clap::Parser;
+use enum_dispatch::enum_dispatch;
+use ulid::Generator;
+
+use crate::{
+    process_id_decode, process_nanoid, process_ulid, process_uuid, CmdExector, SnowflakeGenerator,
+    SNOWFLAKE_EPOCH,
+};
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExector)]
+pub enum IdSubCommand {
+    #[command(about = "Generate random (v4) or time ordered (v7) UUIDs")]
+    Uuid(IdUuidOpts),
+
+    #[command(about = "Generate ULIDs")]
+    Ulid(IdUlidOpts),
+
+    #[command(about = "Generate nanoids")]
+    Nanoid(IdNanoidOpts),
+
+    #[command(about = "Generate Twitter style snowflake ids")]
+    Snowflake(IdSnowflakeOpts),
+
+    #[command(about = "Show the timestamp embedded in a UUIDv7, ULID or snowflake id")]
+    Decode(IdDecodeOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct IdUuidOpts {
+    #[arg(long, value_parser = parse_uuid_version, default_value = "4")]
+    pub version: UuidVersion,
+
+    #[arg(short, long, default_value_t = 1)]
+    pub count: usize,
+}
+
+#[derive(Debug, Parser)]
+pub struct IdUlidOpts {
+    #[arg(short, long, default_value_t = 1)]
+    pub count: usize,
+}
+
+#[derive(Debug, Parser)]
+pub struct IdNanoidOpts {
+    #[arg(long, default_value_t = 21)]
+    pub size: usize,
+
+    /// Characters to pick from, the URL safe alphabet `A-Za-z0-9_-` by default
+    #[arg(long)]
+    pub alphabet: Option<String>,
+
+    #[arg(short, long, default_value_t = 1)]
+    pub count: usize,
+}
+
+#[derive(Debug, Parser)]
+pub struct IdSnowflakeOpts {
+    /// Worker id between 0 and 1023
+    #[arg(long, default_value_t = 0)]
+    pub worker_id: u64,
+
+    /// Custom epoch in unix milliseconds
+    #[arg(long, default_value_t = SNOWFLAKE_EPOCH)]
+    pub epoch: u64,
+
+    #[arg(short, long, default_value_t = 1)]
+    pub count: usize,
+}
+
+#[derive(Debug, Parser)]
+pub struct IdDecodeOpts {
+    pub id: String,
+
+    /// Custom snowflake epoch in unix milliseconds
+    #[arg(long, default_value_t = SNOWFLAKE_EPOCH)]
+    pub epoch: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum UuidVersion {
+    V4,
+    V7,
+}
+
+impl CmdExector for IdUuidOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        for _ in 0..self.count {
+            println!("{}", process_uuid(self.version));
+        }
+        Ok(())
+    }
+}
+
+impl CmdExector for IdUlidOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        // 同一个 generator 生成，同一毫秒内的 ULID 也是递增的
+        let mut generator = Generator::new();
+        for _ in 0..self.count {
+            println!("{}", process_ulid(&mut generator)?);
+        }
+        Ok(())
+    }
+}
+
+impl CmdExector for IdNanoidOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        for _ in 0..self.count {
+            println!("{}", process_nanoid(self.size, self.alphabet.as_deref())?);
+        }
+        Ok(())
+    }
+}
+
+impl CmdExector for IdSnowflakeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let mut generator = SnowflakeGenerator::new(self.worker_id, self.epoch)?;
+        for _ in 0..self.count {
+            println!("{}", generator.next_id()?);
+        }
+        Ok(())
+    }
+}
+
+impl CmdExector for IdDecodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let info = process_id_decode(&self.id, self.epoch)?;
+        println!("type: {}", info.kind);
+        println!("timestamp: {}", info.timestamp.to_rfc3339());
+        println!("unix ms: {}", info.timestamp.timestamp_millis());
+        if let Some((worker_id, sequence)) = info.snowflake {
+            println!("worker id: {}", worker_id);
+            println!("sequence: {}", sequence);
+        }
+        Ok(())
+    }
+}
+
+fn parse_uuid_version(version: &str) -> Result<UuidVersion, anyhow::Error> {
+    version.parse()
+}
+
+impl FromStr for UuidVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().trim_start_matches('v') {
+            "4" => Ok(UuidVersion::V4),
+            "7" => Ok(UuidVersion::V7),
+            _ => Err(anyhow::anyhow!(
+                "Invalid UUID version, only 4 and 7 are supported"
+            )),
+        }
+    }
+}
+
+impl From<UuidVersion> for &'static str {
+    fn from(value: UuidVersion) -> Self {
+        match value {
+            UuidVersion::V4 => "4",
+            UuidVersion::V7 => "7",
+        }
+    }
+}
+
+impl fmt::Display for UuidVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
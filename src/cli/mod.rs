@@ -1,16 +1,27 @@
 mod base64;
+mod codec;
+mod compress;
 mod csv;
 mod genpass;
 mod http;
+mod id;
 mod jwt;
+mod otp;
+mod pem;
 mod text;
+mod url;
 
 use std::path::{Path, PathBuf};
 
-use clap::Parser;
+use clap::{Args, Parser};
 use enum_dispatch::enum_dispatch;
 
-pub use self::{base64::*, csv::*, genpass::*, http::*, jwt::*, text::*};
+use crate::{Argon2Params, MAX_ARGON2_ITERATIONS, MAX_ARGON2_MEMORY, MAX_ARGON2_PARALLELISM};
+
+pub use self::{
+    base64::*, codec::*, compress::*, csv::*, genpass::*, http::*, id::*, jwt::*, otp::*, pem::*,
+    text::*, url::*,
+};
 
 #[derive(Debug, Parser)]
 #[clap(name = "rcli", version, about, long_about = None)]
@@ -19,6 +30,8 @@ pub struct Opts {
     pub cmd: SubCommand,
 }
 
+// 命令行参数只解析一次，variant 大小差距无所谓
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Parser)]
 #[enum_dispatch(CmdExector)]
 pub enum SubCommand {
@@ -35,6 +48,18 @@ pub enum SubCommand {
     #[command(subcommand, about = "Base64 encode/decode")]
     Base64(Base64SubCommand),
 
+    #[command(about = "Encode data as hex, base32, base58, base85, bech32 or quoted-printable")]
+    Encode(EncodeOpts),
+
+    #[command(about = "Decode hex, base32, base58, base85, bech32 or quoted-printable text")]
+    Decode(DecodeOpts),
+
+    #[command(about = "Compress data with gzip, zlib, zstd or brotli")]
+    Compress(CompressOpts),
+
+    #[command(about = "Decompress gzip, zlib, zstd or brotli data")]
+    Decompress(DecompressOpts),
+
     #[command(subcommand, about = "Text sign/verify")]
     Text(TextSubCommand),
 
@@ -43,6 +68,18 @@ pub enum SubCommand {
 
     #[command(subcommand, about = "JWT sign/verify")]
     Jwt(JwtSubCommand),
+
+    #[command(subcommand, about = "TOTP/HOTP one-time passwords")]
+    Otp(OtpSubCommand),
+
+    #[command(subcommand, about = "Generate or decode unique identifiers")]
+    Id(IdSubCommand),
+
+    #[command(subcommand, about = "Wrap DER data in PEM, or unwrap PEM files")]
+    Pem(PemSubCommand),
+
+    #[command(subcommand, about = "URL percent-encoding and parsing")]
+    Url(UrlSubCommand),
 }
 
 fn parse_duration(duration_str: &str) -> Result<std::time::Duration, &'static str> {
@@ -68,6 +105,35 @@ fn parse_duration(duration_str: &str) -> Result<std::time::Duration, &'static st
     Ok(std::time::Duration::from_secs(duration * mul_unit))
 }
 
+/// Argon2id cost options shared by `genpass derive` and `text encrypt --password`
+#[derive(Debug, Args)]
+pub struct Argon2Opts {
+    /// Argon2id memory cost in KiB
+    #[arg(long, default_value_t = Argon2Params::default().memory,
+        value_parser = clap::value_parser!(u32).range(8..=MAX_ARGON2_MEMORY as i64))]
+    pub memory: u32,
+
+    /// Argon2id iterations
+    #[arg(long, default_value_t = Argon2Params::default().iterations,
+        value_parser = clap::value_parser!(u32).range(1..=MAX_ARGON2_ITERATIONS as i64))]
+    pub iterations: u32,
+
+    /// Argon2id parallelism
+    #[arg(long, default_value_t = Argon2Params::default().parallelism,
+        value_parser = clap::value_parser!(u32).range(1..=MAX_ARGON2_PARALLELISM as i64))]
+    pub parallelism: u32,
+}
+
+impl From<&Argon2Opts> for Argon2Params {
+    fn from(value: &Argon2Opts) -> Self {
+        Self {
+            memory: value.memory,
+            iterations: value.iterations,
+            parallelism: value.parallelism,
+        }
+    }
+}
+
 fn verify_file(filename: &str) -> Result<String, &'static str> {
     // if input is "-" or file exists
     if filename == "-" || Path::new(filename).exists() {
@@ -77,6 +143,23 @@ fn verify_file(filename: &str) -> Result<String, &'static str> {
     }
 }
 
+/// Max bytes shown when binary data is decoded to a terminal
+const PREVIEW_LEN: usize = 256;
+
+/// Show decoded data on a terminal: text is printed as is, binary data as a hexdump preview
+fn print_decoded(data: &[u8]) {
+    // 终端上直接输出二进制会乱码
+    match std::str::from_utf8(data) {
+        Ok(text) if !text.contains(|c: char| c.is_control() && !c.is_whitespace()) => {
+            println!("{}", text)
+        }
+        _ => {
+            print!("{}", crate::hexdump(data, PREVIEW_LEN));
+            eprintln!("Binary data, use -o <file> or a pipe to get the raw bytes");
+        }
+    }
+}
+
 fn verify_path(path: &str) -> Result<PathBuf, &'static str> {
     // if input is "-" or file exists
     let p = Path::new(path);
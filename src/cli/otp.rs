@@ -0,0 +1,210 @@
+use std::{
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::{Args, Parser};
+use enum_dispatch::enum_dispatch;
+
+use crate::{
+    process_hotp, process_hotp_verify, process_otp_decode_secret, process_otp_generate_secret,
+    process_otp_uri, process_totp, process_totp_verify, CmdExector, OtpParams, MAX_OTP_SKEW,
+};
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExector)]
+pub enum OtpSubCommand {
+    #[command(about = "Generate a base32 secret and its otpauth:// URI")]
+    GenerateSecret(OtpGenerateSecretOpts),
+
+    #[command(about = "Print the current TOTP code, or the HOTP code with --counter")]
+    Code(OtpCodeOpts),
+
+    #[command(about = "Verify a TOTP code, or a HOTP code with --counter")]
+    Verify(OtpVerifyOpts),
+}
+
+/// Algorithm, digits and period, they must match what the authenticator app uses
+#[derive(Debug, Args)]
+pub struct OtpParamsOpts {
+    #[arg(long, value_parser = parse_otp_algorithm, default_value = "sha1")]
+    pub algorithm: OtpAlgorithm,
+
+    #[arg(long, default_value_t = 6, value_parser = clap::value_parser!(u32).range(6..=10))]
+    pub digits: u32,
+
+    /// TOTP time step in seconds
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+    pub period: u64,
+}
+
+#[derive(Debug, Parser)]
+pub struct OtpGenerateSecretOpts {
+    /// Number of random bytes in the secret
+    #[arg(long, default_value_t = 20)]
+    pub bytes: usize,
+
+    #[arg(long, default_value = "rcli")]
+    pub issuer: String,
+
+    #[arg(long)]
+    pub account: String,
+
+    #[command(flatten)]
+    pub params: OtpParamsOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct OtpCodeOpts {
+    /// Base32 secret
+    #[arg(short, long)]
+    pub secret: String,
+
+    /// Use HOTP with this counter instead of TOTP
+    #[arg(long)]
+    pub counter: Option<u64>,
+
+    /// Unix timestamp used instead of the current time, TOTP only
+    #[arg(long, conflicts_with = "counter")]
+    pub time: Option<u64>,
+
+    #[command(flatten)]
+    pub params: OtpParamsOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct OtpVerifyOpts {
+    /// Base32 secret
+    #[arg(short, long)]
+    pub secret: String,
+
+    #[arg(long)]
+    pub code: String,
+
+    /// Use HOTP with this counter instead of TOTP
+    #[arg(long)]
+    pub counter: Option<u64>,
+
+    /// Unix timestamp used instead of the current time, TOTP only
+    #[arg(long, conflicts_with = "counter")]
+    pub time: Option<u64>,
+
+    /// Accepted time steps before/after now for TOTP, or counters ahead for HOTP
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(0..=MAX_OTP_SKEW))]
+    pub skew: u64,
+
+    #[command(flatten)]
+    pub params: OtpParamsOpts,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum OtpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl From<&OtpParamsOpts> for OtpParams {
+    fn from(opts: &OtpParamsOpts) -> Self {
+        Self {
+            algorithm: opts.algorithm,
+            digits: opts.digits,
+            period: opts.period,
+        }
+    }
+}
+
+fn now() -> anyhow::Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+impl CmdExector for OtpGenerateSecretOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let secret = process_otp_generate_secret(self.bytes)?;
+        let uri = process_otp_uri(&secret, &self.issuer, &self.account, (&self.params).into());
+        println!("{}", secret);
+        println!("{}", uri);
+        Ok(())
+    }
+}
+
+impl CmdExector for OtpCodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let key = process_otp_decode_secret(&self.secret)?;
+        let params = OtpParams::from(&self.params);
+        let code = match self.counter {
+            Some(counter) => process_hotp(&key, counter, params)?,
+            None => {
+                let time = match self.time {
+                    Some(time) => time,
+                    None => now()?,
+                };
+                let code = process_totp(&key, time, params)?;
+                eprintln!("Valid for {}s", params.period - time % params.period);
+                code
+            }
+        };
+        println!("{}", code);
+        Ok(())
+    }
+}
+
+impl CmdExector for OtpVerifyOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let key = process_otp_decode_secret(&self.secret)?;
+        let params = OtpParams::from(&self.params);
+        let code = self.code.trim();
+        match self.counter {
+            Some(counter) => match process_hotp_verify(&key, code, counter, self.skew, params)? {
+                // 验证通过后服务端要把 counter 更新为匹配值 + 1
+                Some(matched) => println!("✓ Code is valid, next counter is {}", matched + 1),
+                None => anyhow::bail!("Code is invalid"),
+            },
+            None => {
+                let time = match self.time {
+                    Some(time) => time,
+                    None => now()?,
+                };
+                match process_totp_verify(&key, code, time, self.skew, params)? {
+                    Some(offset) => println!("✓ Code is valid, time step offset {}", offset),
+                    None => anyhow::bail!("Code is invalid"),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_otp_algorithm(algorithm: &str) -> Result<OtpAlgorithm, anyhow::Error> {
+    algorithm.parse()
+}
+
+impl FromStr for OtpAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sha1" => Ok(OtpAlgorithm::Sha1),
+            "sha256" => Ok(OtpAlgorithm::Sha256),
+            "sha512" => Ok(OtpAlgorithm::Sha512),
+            _ => Err(anyhow::anyhow!("Invalid algorithm")),
+        }
+    }
+}
+
+impl From<OtpAlgorithm> for &'static str {
+    fn from(value: OtpAlgorithm) -> Self {
+        match value {
+            OtpAlgorithm::Sha1 => "sha1",
+            OtpAlgorithm::Sha256 => "sha256",
+            OtpAlgorithm::Sha512 => "sha512",
+        }
+    }
+}
+
+impl fmt::Display for OtpAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
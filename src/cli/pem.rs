@@ -0,0 +1,104 @@
+use std::io::{IsTerminal, Write};
+
+use clap::Parser;
+use enum_dispatch::enum_dispatch;
+
+use crate::{get_content, get_writer, process_pem_decode, process_pem_encode, CmdExector};
+
+use super::{print_decoded, verify_file};
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExector)]
+pub enum PemSubCommand {
+    #[command(about = "Wrap DER data in BEGIN/END lines")]
+    Encode(PemEncodeOpts),
+
+    #[command(about = "List the blocks of a PEM file and write the DER payload of one")]
+    Decode(PemDecodeOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct PemEncodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    /// Type of the data, e.g. "PUBLIC KEY", "PRIVATE KEY" or "CERTIFICATE"
+    #[arg(short, long)]
+    pub label: String,
+
+    /// Header line such as "Proc-Type: 4,ENCRYPTED", can be repeated
+    #[arg(short = 'H', long = "header", value_parser = parse_header)]
+    pub headers: Vec<(String, String)>,
+
+    /// Accept a payload that isn't well-formed DER
+    #[arg(long)]
+    pub no_validate: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct PemDecodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    /// Which block to write when the file has several, starting at 0
+    #[arg(long)]
+    pub index: Option<usize>,
+
+    /// Write the DER payload to this file, `-` is stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+
+    /// Accept payloads that aren't well-formed DER
+    #[arg(long)]
+    pub no_validate: bool,
+}
+
+impl CmdExector for PemEncodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let content = get_content(&self.input)?;
+        let pem = process_pem_encode(&content, &self.label, &self.headers, !self.no_validate)?;
+        print!("{}", pem);
+        Ok(())
+    }
+}
+
+impl CmdExector for PemDecodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let content = get_content(&self.input)?;
+        let pems = process_pem_decode(&content, !self.no_validate)?;
+        // 块的信息写到 stderr，stdout 只留 DER 数据
+        for (i, pem) in pems.iter().enumerate() {
+            eprintln!("[{}] {}, {} bytes", i, pem.tag(), pem.contents().len());
+            for (key, value) in pem.headers().iter() {
+                eprintln!("    {}: {}", key, value);
+            }
+        }
+
+        let index = match self.index {
+            Some(index) => index,
+            None if pems.len() == 1 => 0,
+            None => anyhow::bail!("Found {} PEM blocks, pick one with --index", pems.len()),
+        };
+        let Some(pem) = pems.get(index) else {
+            anyhow::bail!("No block {}, the input has {} blocks", index, pems.len());
+        };
+
+        if self.output == "-" && std::io::stdout().is_terminal() {
+            print_decoded(pem.contents());
+        } else {
+            let mut writer = get_writer(&self.output)?;
+            writer.write_all(pem.contents())?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_header(header: &str) -> Result<(String, String), &'static str> {
+    match header.split_once(':') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err("Header must look like \"Key: Value\""),
+    }
+}
@@ -1,16 +1,17 @@
 use std::{fmt, path::PathBuf, str::FromStr};
 
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
-use clap::Parser;
+use clap::{Args, Parser};
 use enum_dispatch::enum_dispatch;
 use tokio::fs;
 
 use crate::{
-    get_content, get_reader, process_text_decrypt, process_text_encrypt, process_text_key_generate,
-    process_text_sign, process_text_verify, CmdExector,
+    get_content, get_reader, get_secret, process_text_decrypt, process_text_decrypt_password,
+    process_text_encrypt, process_text_encrypt_password, process_text_key_generate,
+    process_text_sign, process_text_verify, Argon2Params, CmdExector,
 };
 
-use super::{verify_file, verify_path};
+use super::{verify_file, verify_path, Argon2Opts};
 
 #[derive(Debug, Parser)]
 #[enum_dispatch(CmdExector)]
@@ -24,10 +25,10 @@ pub enum TextSubCommand {
     #[command(about = "Generate a random blake3 key or ed25519 key pair")]
     Generate(KeyGenerateOpts),
 
-    #[command(about = "Encrypt a message with a key")]
+    #[command(about = "Encrypt a message with a key or a password")]
     Encrypt(TextEncryptOpts),
 
-    #[command(about = "Decrypt a message with a key")]
+    #[command(about = "Decrypt a message with a key or a password")]
     Decrypt(TextDecryptOpts),
 }
 
@@ -43,34 +44,70 @@ pub struct TextSignOpts {
     pub format: TextSignFormat,
 }
 
+/// Use a password instead of a key file, the key is derived from it with Argon2id
+#[derive(Debug, Args)]
+pub struct PasswordOpts {
+    /// Prompt for the password without echo
+    #[arg(long, conflicts_with_all = ["key", "password_file", "password_env"])]
+    pub password: bool,
+
+    /// Read the password from a file, trailing line breaks are ignored
+    #[arg(long, value_parser = verify_file, conflicts_with_all = ["key", "password_env"])]
+    pub password_file: Option<String>,
+
+    /// Read the password from this environment variable
+    #[arg(long, conflicts_with = "key")]
+    pub password_env: Option<String>,
+}
+
 #[derive(Debug, Parser)]
 pub struct TextEncryptOpts {
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
 
-    #[arg(short, long, value_parser = verify_file)]
-    pub key: String,
+    // Argon2id 的参数只对密码有效，和 --key 一起用直接报错，别悄悄忽略
+    #[arg(short, long, value_parser = verify_file, conflicts_with = "Argon2Opts",
+        required_unless_present_any = ["password", "password_file", "password_env"])]
+    pub key: Option<String>,
 
-    #[arg(long, value_parser = parse_text_sign_format, default_value = "chacha20")]
-    pub format: TextSignFormat,
+    #[command(flatten)]
+    pub password: PasswordOpts,
+
+    #[command(flatten)]
+    pub argon2: Argon2Opts,
+
+    /// chacha20, xchacha20 or aes256gcm, recorded in the message so decrypt picks it up
+    #[arg(long, alias = "format", value_parser = parse_text_cipher, default_value = "chacha20")]
+    pub cipher: TextCipher,
 
     #[arg(short, long, default_value = "-")]
     pub output: String,
 }
 
+/// The cipher is read from the message, so `--cipher` isn't needed here
 #[derive(Debug, Parser)]
 pub struct TextDecryptOpts {
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
 
-    #[arg(short, long, value_parser = verify_file)]
-    pub key: String,
+    #[arg(short, long, value_parser = verify_file,
+        required_unless_present_any = ["password", "password_file", "password_env"])]
+    pub key: Option<String>,
 
-    #[arg(long, value_parser = parse_text_sign_format, default_value = "chacha20")]
-    pub format: TextSignFormat,
+    /// The Argon2id parameters and salt are read from the message
+    #[command(flatten)]
+    pub password: PasswordOpts,
 
     #[arg(short, long, default_value = "-")]
     pub output: String,
+
+    /// Decrypt a message from older versions, which used the fixed nonce in the key file
+    #[arg(long, conflicts_with_all = ["password", "password_file", "password_env"])]
+    pub legacy: bool,
+
+    /// Ignored, only kept so scripts passing `--format chacha20` to decrypt keep working
+    #[arg(long, alias = "format", value_parser = parse_text_cipher, hide = true)]
+    pub cipher: Option<TextCipher>,
 }
 
 #[derive(Debug, Parser)]
@@ -104,6 +141,13 @@ pub enum TextSignFormat {
     ChaCha20,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextCipher {
+    ChaCha20,
+    XChaCha20,
+    Aes256Gcm,
+}
+
 fn parse_text_sign_format(format: &str) -> Result<TextSignFormat, anyhow::Error> {
     format.parse()
 }
@@ -137,6 +181,64 @@ impl fmt::Display for TextSignFormat {
     }
 }
 
+fn parse_text_cipher(cipher: &str) -> Result<TextCipher, anyhow::Error> {
+    cipher.parse()
+}
+
+impl FromStr for TextCipher {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "chacha20" | "chacha20-poly1305" => Ok(TextCipher::ChaCha20),
+            "xchacha20" | "xchacha20-poly1305" => Ok(TextCipher::XChaCha20),
+            "aes256gcm" | "aes-256-gcm" => Ok(TextCipher::Aes256Gcm),
+            _ => Err(anyhow::anyhow!("Invalid cipher")),
+        }
+    }
+}
+
+impl From<TextCipher> for &'static str {
+    fn from(value: TextCipher) -> Self {
+        match value {
+            TextCipher::ChaCha20 => "chacha20",
+            TextCipher::XChaCha20 => "xchacha20",
+            TextCipher::Aes256Gcm => "aes256gcm",
+        }
+    }
+}
+
+impl fmt::Display for TextCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+impl PasswordOpts {
+    /// The password if one of the options is given, `confirm` asks for it twice at the prompt
+    fn read(&self, confirm: bool) -> anyhow::Result<Option<Vec<u8>>> {
+        let password = if self.password {
+            let password = rpassword::prompt_password("Password: ")?;
+            if confirm && rpassword::prompt_password("Confirm password: ")? != password {
+                anyhow::bail!("Passwords don't match");
+            }
+            password.into_bytes()
+        } else if let Some(path) = &self.password_file {
+            get_secret(path)?
+        } else if let Some(name) = &self.password_env {
+            std::env::var(name)
+                .map_err(|_| anyhow::anyhow!("Environment variable {} is not set", name))?
+                .into_bytes()
+        } else {
+            return Ok(None);
+        };
+        if password.is_empty() {
+            anyhow::bail!("Password can't be empty");
+        }
+        Ok(Some(password))
+    }
+}
+
 impl CmdExector for TextSignOpts {
     async fn execute(self) -> anyhow::Result<()> {
         let mut reader = get_reader(&self.input)?;
@@ -177,8 +279,16 @@ impl CmdExector for KeyGenerateOpts {
 impl CmdExector for TextEncryptOpts {
     async fn execute(self) -> anyhow::Result<()> {
         let mut reader = get_reader(&self.input)?;
-        let key = get_content(&self.key)?;
-        let encrypt_text = process_text_encrypt(&mut reader, &key, self.format)?;
+        let encrypt_text = match (self.password.read(true)?, &self.key) {
+            (Some(password), _) => {
+                let params = Argon2Params::from(&self.argon2);
+                process_text_encrypt_password(&mut reader, &password, params, self.cipher)?
+            }
+            (None, Some(key)) => {
+                process_text_encrypt(&mut reader, &get_content(key)?, self.cipher)?
+            }
+            (None, None) => anyhow::bail!("Either --key or a password is required"),
+        };
         let encoded = URL_SAFE_NO_PAD.encode(encrypt_text);
         if self.output == "-" {
             println!("{}", encoded);
@@ -199,8 +309,14 @@ impl CmdExector for TextDecryptOpts {
         let b64_encrypt_text = String::from_utf8(buf)?;
         let encrypt_text = URL_SAFE_NO_PAD.decode(b64_encrypt_text)?;
 
-        let key = get_content(&self.key)?;
-        let plaintext = process_text_decrypt(&encrypt_text[..], &key, self.format)?;
+        let plaintext = match (self.password.read(false)?, &self.key) {
+            (Some(password), _) => process_text_decrypt_password(&encrypt_text, &password)?,
+            (None, Some(key)) => {
+                let key = get_content(key)?;
+                process_text_decrypt(&encrypt_text, &key, self.legacy)?
+            }
+            (None, None) => anyhow::bail!("Either --key or a password is required"),
+        };
 
         if self.output == "-" {
             println!("\n{}", String::from_utf8(plaintext)?);
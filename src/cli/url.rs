@@ -0,0 +1,134 @@
+use std::{
+    fmt,
+    io::{IsTerminal, Write},
+    str::FromStr,
+};
+
+use clap::Parser;
+use enum_dispatch::enum_dispatch;
+
+use crate::{
+    get_content, get_writer, process_url_decode, process_url_encode, process_url_parse, CmdExector,
+};
+
+use super::{print_decoded, verify_file};
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExector)]
+pub enum UrlSubCommand {
+    #[command(about = "Percent-encode text for a URL component, a full URL or a form")]
+    Encode(UrlEncodeOpts),
+
+    #[command(about = "Remove percent-encoding")]
+    Decode(UrlDecodeOpts),
+
+    #[command(about = "Split a URL into scheme, host, path and query pairs as JSON")]
+    Parse(UrlParseOpts),
+}
+
+/// A trailing line break of the input is ignored, so `echo` can be piped in
+#[derive(Debug, Parser)]
+pub struct UrlEncodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    /// component (only `-._~` kept), full (reserved characters like `/?&=` kept too) or form (space as `+`)
+    #[arg(short, long, value_parser = parse_url_encode_set, default_value = "component")]
+    pub set: UrlEncodeSet,
+}
+
+#[derive(Debug, Parser)]
+pub struct UrlDecodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    /// Decode `+` as a space, as in form data and query strings
+    #[arg(long)]
+    pub form: bool,
+
+    /// Write the decoded bytes to this file, `-` is stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct UrlParseOpts {
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum UrlEncodeSet {
+    Component,
+    Full,
+    Form,
+}
+
+impl CmdExector for UrlEncodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let content = get_content(&self.input)?;
+        println!("{}", process_url_encode(trim_newline(&content), self.set));
+        Ok(())
+    }
+}
+
+impl CmdExector for UrlDecodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let content = String::from_utf8(get_content(&self.input)?)?;
+        let ret = process_url_decode(content.trim(), self.form);
+
+        if self.output == "-" && std::io::stdout().is_terminal() {
+            print_decoded(&ret);
+        } else {
+            let mut writer = get_writer(&self.output)?;
+            writer.write_all(&ret)?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl CmdExector for UrlParseOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let parts = process_url_parse(&self.url)?;
+        println!("{}", serde_json::to_string_pretty(&parts)?);
+        Ok(())
+    }
+}
+
+fn trim_newline(data: &[u8]) -> &[u8] {
+    let data = data.strip_suffix(b"\n").unwrap_or(data);
+    data.strip_suffix(b"\r").unwrap_or(data)
+}
+
+fn parse_url_encode_set(set: &str) -> Result<UrlEncodeSet, anyhow::Error> {
+    set.parse()
+}
+
+impl FromStr for UrlEncodeSet {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "component" => Ok(UrlEncodeSet::Component),
+            "full" => Ok(UrlEncodeSet::Full),
+            "form" => Ok(UrlEncodeSet::Form),
+            _ => Err(anyhow::anyhow!("Invalid encode set")),
+        }
+    }
+}
+
+impl From<UrlEncodeSet> for &'static str {
+    fn from(value: UrlEncodeSet) -> Self {
+        match value {
+            UrlEncodeSet::Component => "component",
+            UrlEncodeSet::Full => "full",
+            UrlEncodeSet::Form => "form",
+        }
+    }
+}
+
+impl fmt::Display for UrlEncodeSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
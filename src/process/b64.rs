@@ -1,37 +1,179 @@
-use std::io::Read;
+use std::io::{self, Read, Write};
 
 use anyhow::Result;
 use base64::{
-    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    read::DecoderReader,
+    write::EncoderWriter,
     Engine as _,
 };
 
-use crate::Base64Format;
+use crate::{process_decompress_auto, Base64Format, Codec, Compression};
 
-pub fn process_encode(reader: &mut dyn Read, format: Base64Format) -> Result<String> {
-    let mut buf = Vec::new();
-    reader.read_to_end(&mut buf)?;
+/// Drops ascii whitespace from the wrapped reader, so wrapped lines can be decoded as a stream
+struct SkipWhitespace<R> {
+    inner: R,
+}
 
-    let encoded = match format {
-        Base64Format::Standard => STANDARD.encode(buf),
-        Base64Format::UrlSafe => URL_SAFE_NO_PAD.encode(buf),
-    };
-    Ok(encoded)
+impl<R: Read> Read for SkipWhitespace<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.inner.read(buf)?;
+            if n == 0 {
+                return Ok(0);
+            }
+            // 原地压缩，只保留非空白字符；整块都是空白时继续读，返回 0 会被当成 EOF
+            let mut kept = 0;
+            for i in 0..n {
+                if !buf[i].is_ascii_whitespace() {
+                    buf[kept] = buf[i];
+                    kept += 1;
+                }
+            }
+            if kept > 0 {
+                return Ok(kept);
+            }
+        }
+    }
 }
 
-pub fn process_decode(reader: &mut dyn Read, format: Base64Format) -> Result<String> {
-    let mut buf = String::new();
-    reader.read_to_string(&mut buf)?;
-    // avoid accidental newlines
-    let buf = buf.trim();
+/// Inserts a line break after every `width` bytes written, the last line is left unterminated
+struct LineWrapper<W> {
+    inner: W,
+    width: usize,
+    column: usize,
+}
 
-    let decoded = match format {
-        Base64Format::Standard => STANDARD.decode(buf)?,
-        Base64Format::UrlSafe => URL_SAFE_NO_PAD.decode(buf)?,
-    };
+impl<W: Write> Write for LineWrapper<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.width == 0 {
+            return self.inner.write(buf);
+        }
+        // EncoderWriter 把部分写入当成需要重试，所以这里一次写完整个 buf
+        let mut rest = buf;
+        while !rest.is_empty() {
+            // 行满了之后等到下一个字节才换行，这样结尾不会多出空行
+            if self.column == self.width {
+                self.inner.write_all(b"\n")?;
+                self.column = 0;
+            }
+            let len = rest.len().min(self.width - self.column);
+            self.inner.write_all(&rest[..len])?;
+            self.column += len;
+            rest = &rest[len..];
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Base64 as a `Codec`, encoding and decoding stream chunk by chunk
+struct Base64Codec {
+    engine: GeneralPurpose,
+    /// Line length of encoded output, 0 for a single line
+    wrap: usize,
+}
+
+impl Base64Format {
+    /// Standard output is padded and url safe output isn't, unless `pad` says otherwise
+    pub(crate) fn engine(&self, pad: Option<bool>) -> GeneralPurpose {
+        let (alphabet, default_pad) = match self {
+            Base64Format::Standard => (&alphabet::STANDARD, true),
+            Base64Format::UrlSafe => (&alphabet::URL_SAFE, false),
+        };
+        // 解码时没有指定就两种都接受
+        let decode_mode = match pad {
+            Some(true) => DecodePaddingMode::RequireCanonical,
+            Some(false) => DecodePaddingMode::RequireNone,
+            None => DecodePaddingMode::Indifferent,
+        };
+        let config = GeneralPurposeConfig::new()
+            .with_encode_padding(pad.unwrap_or(default_pad))
+            .with_decode_padding_mode(decode_mode);
+        GeneralPurpose::new(alphabet, config)
+    }
+
+    /// `wrap` is the line length, 76 for MIME, 64 for PEM and 0 for a single line
+    pub fn codec(&self, pad: Option<bool>, wrap: usize) -> Box<dyn Codec> {
+        Box::new(Base64Codec {
+            engine: self.engine(pad),
+            wrap,
+        })
+    }
+}
+
+impl Codec for Base64Codec {
+    fn encode(&self, data: &[u8]) -> Result<String> {
+        let mut ret = Vec::new();
+        self.encode_stream(&mut &data[..], &mut ret)?;
+        Ok(String::from_utf8(ret)?)
+    }
+
+    fn decode(&self, text: &str) -> Result<Vec<u8>> {
+        Ok(self.engine.decode(text)?)
+    }
+
+    fn encode_stream(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+        let writer = LineWrapper {
+            inner: writer,
+            width: self.wrap,
+            column: 0,
+        };
+        let mut encoder = EncoderWriter::new(writer, &self.engine);
+        io::copy(reader, &mut encoder)?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    fn decode_stream<'a>(&'a self, reader: &'a mut dyn Read) -> Result<Box<dyn Read + 'a>> {
+        Ok(Box::new(DecoderReader::new(
+            SkipWhitespace { inner: reader },
+            &self.engine,
+        )))
+    }
+}
 
-    // TODO: decoded data might not be string (but for this example, we assume it is)
-    Ok(String::from_utf8(decoded)?)
+/// Encode everything from `reader` into `writer`, base64 memory use doesn't grow with the input
+pub fn process_encode(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    codec: &dyn Codec,
+) -> Result<()> {
+    codec.encode_stream(reader, writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Decode text from `reader` into raw bytes in `writer`, whitespace and line breaks are ignored
+///
+/// With `decompress`, gzip, zlib and zstd payloads are decompressed and the format is returned,
+/// data that only starts with their magic bytes is written as is
+pub fn process_decode(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    codec: &dyn Codec,
+    decompress: bool,
+) -> Result<Option<Compression>> {
+    let decoder = codec.decode_stream(reader)?;
+    let (compression, mut decoded) = if decompress {
+        process_decompress_auto(decoder)?
+    } else {
+        (None, decoder)
+    };
+    io::copy(&mut decoded, writer).map_err(|e| match compression {
+        Some(compression) => anyhow::anyhow!(
+            "Failed to decompress {} data: {}, use --no-decompress to keep it as is",
+            compression,
+            e
+        ),
+        None => e.into(),
+    })?;
+    writer.flush()?;
+    Ok(compression)
 }
 
 #[cfg(test)]
@@ -45,7 +187,9 @@ mod tests {
         let input = "cliff.toml";
         let mut reader = get_reader(input)?;
         let format = Base64Format::Standard;
-        assert!(process_encode(&mut reader, format).is_ok());
+        assert!(
+            process_encode(&mut reader, &mut io::sink(), format.codec(None, 0).as_ref()).is_ok()
+        );
         Ok(())
     }
 
@@ -54,8 +198,149 @@ mod tests {
         let input = "fixtures/urlsafe_b64.txt";
         let mut reader = get_reader(input)?;
         let format = Base64Format::UrlSafe;
-        process_decode(&mut reader, format)?;
+        process_decode(
+            &mut reader,
+            &mut io::sink(),
+            format.codec(None, 0).as_ref(),
+            false,
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_decode_binary() -> Result<()> {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut encoded = Vec::new();
+        process_encode(
+            &mut data.as_slice(),
+            &mut encoded,
+            Base64Format::Standard.codec(None, 0).as_ref(),
+        )?;
+        let mut decoded = Vec::new();
+        process_decode(
+            &mut encoded.as_slice(),
+            &mut decoded,
+            Base64Format::Standard.codec(None, 0).as_ref(),
+            false,
+        )?;
+        assert_eq!(decoded, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_decode_decompress() -> Result<()> {
+        let data = "apiVersion: v1\nkind: Secret\n".repeat(20);
+        let mut compressed =
+            crate::process_compress_reader(data.as_bytes(), Compression::Zstd, None)?;
+        let mut encoded = Vec::new();
+        process_encode(
+            &mut compressed,
+            &mut encoded,
+            Base64Format::Standard.codec(None, 0).as_ref(),
+        )?;
+
+        let mut decoded = Vec::new();
+        let compression = process_decode(
+            &mut encoded.as_slice(),
+            &mut decoded,
+            Base64Format::Standard.codec(None, 0).as_ref(),
+            true,
+        )?;
+        assert_eq!(compression, Some(Compression::Zstd));
+        assert_eq!(decoded, data.as_bytes());
+
+        // magic bytes 不完整的原样返回，对得上但解压失败的报错
+        let codec = Base64Format::Standard.codec(None, 0);
+        let raw = [0x1f, 0x8b, 0x00, 0xff];
+        let encoded = codec.encode(&raw)?;
+        let mut decoded = Vec::new();
+        let compression =
+            process_decode(&mut encoded.as_bytes(), &mut decoded, codec.as_ref(), true)?;
+        assert_eq!(compression, None);
+        assert_eq!(decoded, raw);
+
+        let encoded = codec.encode(b"x^y means x to the power y")?;
+        let ret = process_decode(
+            &mut encoded.as_bytes(),
+            &mut Vec::new(),
+            codec.as_ref(),
+            true,
+        );
+        assert!(ret.unwrap_err().to_string().contains("--no-decompress"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_decode_streaming() -> Result<()> {
+        // 比 io::copy 的缓冲区大很多，换行会落在各个 chunk 的中间
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let mut encoded = Vec::new();
+        process_encode(
+            &mut data.as_slice(),
+            &mut encoded,
+            Base64Format::UrlSafe.codec(None, 0).as_ref(),
+        )?;
+        let wrapped: Vec<u8> = encoded
+            .chunks(76)
+            .flat_map(|line| line.iter().chain(b"\r\n  "))
+            .copied()
+            .collect();
+
+        let mut decoded = Vec::new();
+        process_decode(
+            &mut wrapped.as_slice(),
+            &mut decoded,
+            Base64Format::UrlSafe.codec(None, 0).as_ref(),
+            false,
+        )?;
+        assert_eq!(decoded, data);
+
+        let mut invalid = "aGVs*bG8=".as_bytes();
+        assert!(process_decode(
+            &mut invalid,
+            &mut io::sink(),
+            Base64Format::Standard.codec(None, 0).as_ref(),
+            false
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_encode_wrap_and_padding() -> Result<()> {
+        let data = [0xfbu8; 100];
+        let mut encoded = Vec::new();
+        process_encode(
+            &mut &data[..],
+            &mut encoded,
+            Base64Format::UrlSafe.codec(Some(true), 76).as_ref(),
+        )?;
+        let encoded = String::from_utf8(encoded)?;
+        let lines: Vec<&str> = encoded.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), 76);
+        assert!(lines[1].ends_with("-w=="));
 
+        // 不指定 padding 时两种都能解码，指定了就必须一致
+        for (input, pad) in [("-_-_-w==", Some(true)), ("-_-_-w", Some(false))] {
+            let mut decoded = Vec::new();
+            process_decode(
+                &mut input.as_bytes(),
+                &mut decoded,
+                Base64Format::UrlSafe.codec(None, 0).as_ref(),
+                false,
+            )?;
+            assert_eq!(decoded, [0xfb, 0xff, 0xbf, 0xfb]);
+            let mismatch = pad.map(|p| !p);
+            assert!(process_decode(
+                &mut input.as_bytes(),
+                &mut io::sink(),
+                Base64Format::UrlSafe.codec(mismatch, 0).as_ref(),
+                false
+            )
+            .is_err());
+        }
         Ok(())
     }
 }
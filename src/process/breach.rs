@@ -0,0 +1,121 @@
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::{BufRead, BufReader, Seek, SeekFrom},
+};
+
+use anyhow::Result;
+use sha1::{Digest, Sha1};
+
+use super::gen_pass::{retry, MAX_ATTEMPTS};
+
+/// A local Have I Been Pwned dump in the "SHA-1 ordered by hash" format, one `HASH:COUNT` per line
+///
+/// 文件有几十 GB，不能读进内存，所以直接在文件上按字节偏移二分查找
+pub struct BreachCorpus {
+    reader: BufReader<File>,
+    len: u64,
+}
+
+impl BreachCorpus {
+    pub fn open(path: &str) -> Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            reader: BufReader::new(file),
+            len,
+        })
+    }
+
+    /// How many times the password appears in the corpus, 0 if it doesn't
+    pub fn count(&mut self, password: &str) -> Result<u64> {
+        let hash = Sha1::digest(password.as_bytes());
+        let target: String = hash.iter().map(|b| format!("{:02X}", b)).collect();
+
+        // 不变式：如果目标行存在，它的起始偏移一定在 [lo, hi) 里
+        let (mut lo, mut hi) = (0, self.len);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let Some((start, next, line)) = self.line_at(mid)? else {
+                hi = mid;
+                continue;
+            };
+            if start >= hi {
+                hi = mid;
+                continue;
+            }
+
+            let (line_hash, count) = line.split_once(':').unwrap_or((&line, "0"));
+            match line_hash.to_ascii_uppercase().as_str().cmp(&target) {
+                Ordering::Equal => return Ok(count.trim().parse()?),
+                Ordering::Less => lo = next,
+                Ordering::Greater => hi = mid,
+            }
+        }
+        Ok(0)
+    }
+
+    /// The first complete line starting at or after `offset`, with its start and the start of the next line
+    fn line_at(&mut self, offset: u64) -> Result<Option<(u64, u64, String)>> {
+        let mut start = offset;
+        if offset > 0 {
+            // 从 offset - 1 开始跳过半行，这样 offset 正好是行首时也不会漏掉这一行
+            self.reader.seek(SeekFrom::Start(offset - 1))?;
+            let mut skipped = Vec::new();
+            start = offset - 1 + self.reader.read_until(b'\n', &mut skipped)? as u64;
+        } else {
+            self.reader.seek(SeekFrom::Start(0))?;
+        }
+
+        let mut line = String::new();
+        let n = self.reader.read_line(&mut line)?;
+        if n == 0 {
+            return Ok(None);
+        }
+        Ok(Some((start, start + n as u64, line.trim_end().to_string())))
+    }
+}
+
+/// Call `generate` until the candidate isn't found in the breach corpus
+pub fn process_genpass_not_breached(
+    corpus: &mut BreachCorpus,
+    mut generate: impl FnMut() -> Result<String>,
+) -> Result<String> {
+    retry(MAX_ATTEMPTS, || {
+        let password = generate()?;
+        Ok((corpus.count(&password)? == 0).then_some(password))
+    })?
+    .ok_or_else(|| {
+        anyhow::anyhow!(
+            "Failed to generate a password missing from the breach corpus after {} attempts",
+            MAX_ATTEMPTS
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breach_corpus_count() -> Result<()> {
+        let mut corpus = BreachCorpus::open("fixtures/pwned_sample.txt")?;
+        assert_eq!(corpus.count("password")?, 9659365);
+        assert_eq!(corpus.count("football")?, 442134);
+        assert_eq!(corpus.count("abc123")?, 3053096);
+        assert_eq!(corpus.count("V9#qk_Lm2@xzP7!r")?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_not_breached() -> Result<()> {
+        let mut corpus = BreachCorpus::open("fixtures/pwned_sample.txt")?;
+        let mut candidates = vec!["V9#qk_Lm2@xzP7!r", "letmein", "123456"];
+        let password = process_genpass_not_breached(&mut corpus, || {
+            Ok(candidates.pop().unwrap_or_default().into())
+        })?;
+        assert_eq!(password, "V9#qk_Lm2@xzP7!r");
+        assert!(process_genpass_not_breached(&mut corpus, || Ok("dragon".into())).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,467 @@
+use std::io::{Cursor, Read, Write};
+
+use anyhow::Result;
+use bech32::{primitives::decode::CheckedHrpstring, Bech32, Bech32m, Hrp};
+use data_encoding::{Specification, BASE32_NOPAD, HEXLOWER, HEXLOWER_PERMISSIVE, HEXUPPER};
+use quoted_printable::ParseMode;
+
+use crate::{Base64Format, Encoding};
+
+const ASCII85_ALPHABET: &[u8; 85] =
+    b"!\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstu";
+const Z85_ALPHABET: &[u8; 85] =
+    b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+
+/// A binary-to-text encoding
+pub trait Codec {
+    fn encode(&self, data: &[u8]) -> Result<String>;
+
+    /// Decode `text`, ascii whitespace has already been removed unless `keeps_whitespace`
+    fn decode(&self, text: &str) -> Result<Vec<u8>>;
+
+    /// Whitespace is part of the encoded data, e.g. line breaks in quoted-printable
+    fn keeps_whitespace(&self) -> bool {
+        false
+    }
+
+    /// Encode everything from `reader` into `writer`, by default the whole input is read first
+    fn encode_stream(&self, reader: &mut dyn Read, writer: &mut dyn Write) -> Result<()> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        writer.write_all(self.encode(&buf)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Reader over the decoded bytes of `reader`, by default the whole input is read first
+    fn decode_stream<'a>(&'a self, reader: &'a mut dyn Read) -> Result<Box<dyn Read + 'a>> {
+        let mut buf = String::new();
+        reader.read_to_string(&mut buf)?;
+        if !self.keeps_whitespace() {
+            buf.retain(|c| !c.is_ascii_whitespace());
+        }
+        Ok(Box::new(Cursor::new(self.decode(&buf)?)))
+    }
+}
+
+struct Hex {
+    upper: bool,
+}
+
+struct Base32 {
+    crockford: bool,
+}
+
+struct Base58 {
+    check: bool,
+}
+
+struct Base85 {
+    alphabet: &'static [u8; 85],
+    /// Adobe Ascii85: `z` for 4 zero bytes, `<~ ~>` delimiters and a partial last group
+    ascii85: bool,
+}
+
+struct QuotedPrintable;
+
+struct Bech32Codec {
+    hrp: Option<String>,
+    modified: bool,
+}
+
+impl Encoding {
+    /// `hrp` is the human readable part of bech32, required for encoding and checked when decoding
+    pub fn codec(self, hrp: Option<String>) -> Box<dyn Codec> {
+        match self {
+            Encoding::Base64 => Base64Format::Standard.codec(None, 0),
+            Encoding::Base64Url => Base64Format::UrlSafe.codec(None, 0),
+            Encoding::Hex => Box::new(Hex { upper: false }),
+            Encoding::HexUpper => Box::new(Hex { upper: true }),
+            Encoding::Base32 => Box::new(Base32 { crockford: false }),
+            Encoding::Base32Crockford => Box::new(Base32 { crockford: true }),
+            Encoding::Base58 => Box::new(Base58 { check: false }),
+            Encoding::Base58Check => Box::new(Base58 { check: true }),
+            Encoding::Ascii85 => Box::new(Base85 {
+                alphabet: ASCII85_ALPHABET,
+                ascii85: true,
+            }),
+            Encoding::Z85 => Box::new(Base85 {
+                alphabet: Z85_ALPHABET,
+                ascii85: false,
+            }),
+            Encoding::Bech32 => Box::new(Bech32Codec {
+                hrp,
+                modified: false,
+            }),
+            Encoding::Bech32m => Box::new(Bech32Codec {
+                hrp,
+                modified: true,
+            }),
+            Encoding::QuotedPrintable => Box::new(QuotedPrintable),
+        }
+    }
+}
+
+impl Codec for Hex {
+    fn encode(&self, data: &[u8]) -> Result<String> {
+        Ok(match self.upper {
+            true => HEXUPPER.encode(data),
+            false => HEXLOWER.encode(data),
+        })
+    }
+
+    fn decode(&self, text: &str) -> Result<Vec<u8>> {
+        Ok(HEXLOWER_PERMISSIVE.decode(text.as_bytes())?)
+    }
+}
+
+/// Crockford's base32: no I/L/O/U, decoding is case insensitive and reads I/L as 1 and O as 0
+fn crockford() -> Result<data_encoding::Encoding> {
+    let mut spec = Specification::new();
+    spec.symbols.push_str("0123456789ABCDEFGHJKMNPQRSTVWXYZ");
+    spec.translate.from.push_str("abcdefghjkmnpqrstvwxyzIiLlOo");
+    spec.translate.to.push_str("ABCDEFGHJKMNPQRSTVWXYZ111100");
+    spec.ignore.push('-');
+    Ok(spec.encoding()?)
+}
+
+impl Codec for Base32 {
+    fn encode(&self, data: &[u8]) -> Result<String> {
+        Ok(match self.crockford {
+            true => crockford()?.encode(data),
+            false => data_encoding::BASE32.encode(data),
+        })
+    }
+
+    fn decode(&self, text: &str) -> Result<Vec<u8>> {
+        if self.crockford {
+            return Ok(crockford()?.decode(text.as_bytes())?);
+        }
+        // TOTP secret 经常是小写、没有 padding 的
+        let text = text.trim_end_matches('=').to_uppercase();
+        Ok(BASE32_NOPAD.decode(text.as_bytes())?)
+    }
+}
+
+impl Codec for Base58 {
+    fn encode(&self, data: &[u8]) -> Result<String> {
+        let encoder = bs58::encode(data);
+        Ok(match self.check {
+            true => encoder.with_check().into_string(),
+            false => encoder.into_string(),
+        })
+    }
+
+    fn decode(&self, text: &str) -> Result<Vec<u8>> {
+        let decoder = bs58::decode(text);
+        Ok(match self.check {
+            true => decoder.with_check(None).into_vec()?,
+            false => decoder.into_vec()?,
+        })
+    }
+}
+
+impl Codec for Base85 {
+    fn encode(&self, data: &[u8]) -> Result<String> {
+        if !self.ascii85 && !data.len().is_multiple_of(4) {
+            anyhow::bail!("Z85 input length must be a multiple of 4");
+        }
+
+        let mut ret = Vec::with_capacity(data.len() / 4 * 5 + 5);
+        for chunk in data.chunks(4) {
+            let mut group = [0u8; 4];
+            group[..chunk.len()].copy_from_slice(chunk);
+            let mut value = u32::from_be_bytes(group);
+            if self.ascii85 && chunk.len() == 4 && value == 0 {
+                ret.push(b'z');
+                continue;
+            }
+
+            let mut digits = [0u8; 5];
+            for digit in digits.iter_mut().rev() {
+                *digit = self.alphabet[(value % 85) as usize];
+                value /= 85;
+            }
+            // 最后不满 4 字节的一组只输出 n + 1 个字符
+            ret.extend_from_slice(&digits[..chunk.len() + 1]);
+        }
+        Ok(String::from_utf8(ret)?)
+    }
+
+    fn decode(&self, text: &str) -> Result<Vec<u8>> {
+        let mut text = text.as_bytes();
+        if self.ascii85 {
+            text = text.strip_prefix(b"<~").unwrap_or(text);
+            text = text.strip_suffix(b"~>").unwrap_or(text);
+        } else if !text.len().is_multiple_of(5) {
+            anyhow::bail!("Z85 input length must be a multiple of 5");
+        }
+
+        let mut lookup = [None; 256];
+        for (i, &c) in self.alphabet.iter().enumerate() {
+            lookup[c as usize] = Some(i as u64);
+        }
+
+        let mut ret = Vec::with_capacity(text.len() / 5 * 4 + 4);
+        let mut group = Vec::with_capacity(5);
+        for &c in text {
+            if self.ascii85 && c == b'z' && group.is_empty() {
+                ret.extend_from_slice(&[0; 4]);
+                continue;
+            }
+            let Some(digit) = lookup[c as usize] else {
+                anyhow::bail!("Invalid character {:?}", c as char);
+            };
+            group.push(digit);
+            if group.len() == 5 {
+                ret.extend_from_slice(&base85_group(&group)?);
+                group.clear();
+            }
+        }
+
+        if !group.is_empty() {
+            if group.len() == 1 {
+                anyhow::bail!("Invalid length, the last group has a single character");
+            }
+            // 用最大的数字补齐，再丢掉多出来的字节
+            let len = group.len() - 1;
+            group.resize(5, 84);
+            ret.extend_from_slice(&base85_group(&group)?[..len]);
+        }
+        Ok(ret)
+    }
+}
+
+fn base85_group(digits: &[u64]) -> Result<[u8; 4]> {
+    let value = digits.iter().fold(0, |acc, d| acc * 85 + d);
+    let value = u32::try_from(value).map_err(|_| anyhow::anyhow!("Base85 group overflows"))?;
+    Ok(value.to_be_bytes())
+}
+
+/// Segwit addresses (`bc`, `tb` and `bcrt`) carry the witness version in the first symbol,
+/// the data is the witness program
+impl Codec for Bech32Codec {
+    fn encode(&self, data: &[u8]) -> Result<String> {
+        let Some(hrp) = &self.hrp else {
+            anyhow::bail!("Bech32 needs a human readable part, use --hrp");
+        };
+        let hrp = Hrp::parse(hrp)?;
+        // segwit 地址：bech32 是 v0，bech32m 是 v1 (taproot)
+        Ok(match (hrp.is_valid_segwit(), self.modified) {
+            (true, true) => bech32::segwit::encode_v1(hrp, data)?,
+            (true, false) => bech32::segwit::encode_v0(hrp, data)?,
+            (false, true) => bech32::encode::<Bech32m>(hrp, data)?,
+            (false, false) => bech32::encode::<Bech32>(hrp, data)?,
+        })
+    }
+
+    fn decode(&self, text: &str) -> Result<Vec<u8>> {
+        if let Some((hrp, _, program)) = segwit_decode(text)? {
+            self.check_hrp(&hrp)?;
+            return Ok(program);
+        }
+
+        let checked = match self.modified {
+            true => CheckedHrpstring::new::<Bech32m>(text)?,
+            false => CheckedHrpstring::new::<Bech32>(text)?,
+        };
+        self.check_hrp(&checked.hrp())?;
+        Ok(checked.byte_iter().collect())
+    }
+}
+
+impl Bech32Codec {
+    fn check_hrp(&self, actual: &Hrp) -> Result<()> {
+        if let Some(hrp) = &self.hrp {
+            if *actual != Hrp::parse(hrp)? {
+                anyhow::bail!("Expected human readable part {}, got {}", hrp, actual);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// HRP, witness version and witness program if `text` has a segwit HRP
+///
+/// The checksum (bech32 for v0, bech32m for v1+) is chosen by the witness version
+fn segwit_decode(text: &str) -> Result<Option<(Hrp, u8, Vec<u8>)>> {
+    let Some(pos) = text.rfind('1') else {
+        return Ok(None);
+    };
+    match Hrp::parse(&text[..pos].to_lowercase()) {
+        Ok(hrp) if hrp.is_valid_segwit() => {
+            let (hrp, version, program) = bech32::segwit::decode(text)?;
+            Ok(Some((hrp, version.to_u8(), program)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Witness version and program of a segwit address, `None` for other bech32 strings
+pub fn process_segwit_decode(text: &str) -> Result<Option<(u8, Vec<u8>)>> {
+    Ok(segwit_decode(text.trim())?.map(|(_, version, program)| (version, program)))
+}
+
+/// Bare `\n` to `\r\n`, quoted-printable only treats CRLF as a hard line break
+fn to_crlf(data: &[u8]) -> Vec<u8> {
+    let mut ret = Vec::with_capacity(data.len() + data.len() / 32);
+    for (i, &b) in data.iter().enumerate() {
+        if b == b'\n' && (i == 0 || data[i - 1] != b'\r') {
+            ret.push(b'\r');
+        }
+        ret.push(b);
+    }
+    ret
+}
+
+/// Line breaks are LF on both sides, the CRLF of the wire format is handled here
+impl Codec for QuotedPrintable {
+    fn encode(&self, data: &[u8]) -> Result<String> {
+        Ok(quoted_printable::encode_to_str(to_crlf(data)).replace("\r\n", "\n"))
+    }
+
+    fn decode(&self, text: &str) -> Result<Vec<u8>> {
+        let decoded = quoted_printable::decode(to_crlf(text.as_bytes()), ParseMode::Robust)?;
+        let mut ret = Vec::with_capacity(decoded.len());
+        for (i, &b) in decoded.iter().enumerate() {
+            if !(b == b'\r' && decoded.get(i + 1) == Some(&b'\n')) {
+                ret.push(b);
+            }
+        }
+        Ok(ret)
+    }
+
+    fn keeps_whitespace(&self) -> bool {
+        true
+    }
+}
+
+pub fn process_codec_encode(reader: &mut dyn Read, codec: &dyn Codec) -> Result<String> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    codec.encode(&buf)
+}
+
+/// Decode text read from `reader`, whitespace and line breaks anywhere in it are ignored
+/// unless the codec keeps them
+pub fn process_codec_decode(reader: &mut dyn Read, codec: &dyn Codec) -> Result<Vec<u8>> {
+    let mut ret = Vec::new();
+    codec.decode_stream(reader)?.read_to_end(&mut ret)?;
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(encoding: Encoding, data: &[u8], expected: &str) -> Result<()> {
+        let codec = encoding.codec(Some("bc".into()));
+        let encoded = codec.encode(data)?;
+        assert_eq!(encoded, expected, "{}", encoding);
+        assert_eq!(codec.decode(&encoded)?, data, "{}", encoding);
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_known_answers() -> Result<()> {
+        roundtrip(Encoding::Hex, b"\x01\xab", "01ab")?;
+        roundtrip(Encoding::HexUpper, b"\x01\xab", "01AB")?;
+        roundtrip(Encoding::Base32, b"foobar", "MZXW6YTBOI======")?;
+        roundtrip(Encoding::Base58, b"Hello World!", "2NEpo7TZRRrLZSi2U")?;
+        roundtrip(Encoding::Base58Check, b"\x00\x01\x02", "1W8eAT7x")?;
+        roundtrip(Encoding::Ascii85, b"sure.", "F*2M7/c")?;
+        roundtrip(Encoding::Ascii85, &[0, 0, 0, 0, 1], "z!<")?;
+        roundtrip(
+            Encoding::Z85,
+            &[0x86, 0x4F, 0xD2, 0x6F, 0xB5, 0x59, 0xF7, 0x5B],
+            "HelloWorld",
+        )?;
+        roundtrip(Encoding::Base64Url, b"\xfb\xff", "-_8")?;
+        roundtrip(
+            Encoding::QuotedPrintable,
+            "caf\u{e9} = 1\nnext line \n".as_bytes(),
+            "caf=C3=A9 =3D 1\nnext line=20\n",
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_decode_variants() -> Result<()> {
+        let base32 = Encoding::Base32.codec(None);
+        assert_eq!(base32.decode("mzxw6ytboi")?, b"foobar");
+
+        let crockford = Encoding::Base32Crockford.codec(None);
+        let encoded = crockford.encode(b"hello")?;
+        let sloppy = encoded.to_lowercase().replace('1', "l").replace('0', "o");
+        assert_eq!(crockford.decode(&sloppy)?, b"hello");
+
+        let ascii85 = Encoding::Ascii85.codec(None);
+        assert_eq!(ascii85.decode("<~9jqo^~>")?, b"Man ");
+
+        // BIP-173 / BIP-350 里的合法字符串，数据部分为空
+        assert!(Encoding::Bech32.codec(None).decode("A12UEL5L")?.is_empty());
+        assert!(Encoding::Bech32m.codec(None).decode("A1LQFN3A")?.is_empty());
+        assert!(Encoding::Bech32.codec(None).decode("A1LQFN3A").is_err());
+        assert!(Encoding::Bech32m
+            .codec(Some("b".into()))
+            .decode("A1LQFN3A")
+            .is_err());
+
+        let base58check = Encoding::Base58Check.codec(None);
+        assert!(base58check.decode("1W8eAT7y").is_err());
+
+        let mut reader = "01 ab\ncd\n".as_bytes();
+        assert_eq!(
+            process_codec_decode(&mut reader, Encoding::Hex.codec(None).as_ref())?,
+            b"\x01\xab\xcd"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bech32_roundtrip() -> Result<()> {
+        let data: Vec<u8> = (0..20).collect();
+        for encoding in [Encoding::Bech32, Encoding::Bech32m] {
+            let codec = encoding.codec(Some("rcli".into()));
+            let encoded = codec.encode(&data)?;
+            assert!(encoded.starts_with("rcli1"));
+            assert_eq!(codec.decode(&encoded)?, data);
+        }
+        assert!(Encoding::Bech32.codec(None).encode(b"x").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bech32_segwit() -> Result<()> {
+        // BIP-173 P2WPKH 和 BIP-350 P2TR 的测试向量，第一个符号是见证版本，不属于数据
+        let cases = [
+            (
+                Encoding::Bech32,
+                "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4",
+                0,
+                "751e76e8199196d454941c45d1b3a323f1433bd6",
+            ),
+            (
+                Encoding::Bech32m,
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+                1,
+                "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            ),
+        ];
+        for (encoding, address, version, program) in cases {
+            let program = HEXLOWER.decode(program.as_bytes())?;
+            let codec = encoding.codec(Some("bc".into()));
+            assert_eq!(codec.decode(address)?, program);
+            assert_eq!(
+                process_segwit_decode(address)?,
+                Some((version, program.clone()))
+            );
+            assert_eq!(codec.encode(&program)?, address.to_lowercase());
+        }
+        assert!(Encoding::Bech32
+            .codec(Some("tb".into()))
+            .decode(cases[0].1)
+            .is_err());
+        assert_eq!(process_segwit_decode("A12UEL5L")?, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+use std::io::{Cursor, Read};
+
+use anyhow::Result;
+use flate2::read::{GzEncoder, MultiGzDecoder, ZlibDecoder, ZlibEncoder};
+
+use crate::Compression;
+
+const BROTLI_BUFFER_SIZE: usize = 4096;
+const BROTLI_WINDOW_BITS: u32 = 22;
+
+impl Compression {
+    /// Valid levels and the default of the reference tool (gzip, zstd, brotli)
+    fn levels(&self) -> (u32, u32, u32) {
+        match self {
+            Compression::Gzip | Compression::Zlib => (0, 9, 6),
+            Compression::Zstd => (1, 22, 3),
+            Compression::Brotli => (0, 11, 11),
+        }
+    }
+}
+
+/// Compression format from the first bytes of a stream, brotli has no magic bytes
+///
+/// The fixed header fields are checked too, so fewer plain files are mistaken for compressed ones
+pub fn process_detect_compression(header: &[u8]) -> Option<Compression> {
+    match header {
+        // gzip 只定义了 deflate（8）一种压缩方法，flags 的高 3 位是保留位
+        [0x1f, 0x8b, 0x08, flags, ..] if flags & 0xe0 == 0 => Some(Compression::Gzip),
+        [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Compression::Zstd),
+        // 只认 zlib 常见的几个头，避免把以 "x" 开头的文本当成 zlib，第一个 deflate 块的类型也不能是保留的 3
+        [0x78, 0x01 | 0x5e | 0x9c | 0xda, block, ..] if block & 0b110 != 0b110 => {
+            Some(Compression::Zlib)
+        }
+        _ => None,
+    }
+}
+
+/// Reader yielding the compressed bytes of `reader`
+pub fn process_compress_reader<'a, R: Read + 'a>(
+    reader: R,
+    compression: Compression,
+    level: Option<u32>,
+) -> Result<Box<dyn Read + 'a>> {
+    let (min, max, default) = compression.levels();
+    let level = level.unwrap_or(default);
+    if level < min || level > max {
+        anyhow::bail!("{} level must be between {} and {}", compression, min, max);
+    }
+    let reader: Box<dyn Read + 'a> = match compression {
+        Compression::Gzip => Box::new(GzEncoder::new(reader, flate2::Compression::new(level))),
+        Compression::Zlib => Box::new(ZlibEncoder::new(reader, flate2::Compression::new(level))),
+        Compression::Zstd => Box::new(zstd::stream::read::Encoder::new(reader, level as i32)?),
+        Compression::Brotli => Box::new(brotli::CompressorReader::new(
+            reader,
+            BROTLI_BUFFER_SIZE,
+            level,
+            BROTLI_WINDOW_BITS,
+        )),
+    };
+    Ok(reader)
+}
+
+/// Reader yielding the decompressed bytes of `reader`
+pub fn process_decompress_reader<'a, R: Read + 'a>(
+    reader: R,
+    compression: Compression,
+) -> Result<Box<dyn Read + 'a>> {
+    let reader: Box<dyn Read + 'a> = match compression {
+        // gzip 文件可以由多个 member 拼接而成
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Zlib => Box::new(ZlibDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(reader)?),
+        Compression::Brotli => Box::new(brotli::Decompressor::new(reader, BROTLI_BUFFER_SIZE)),
+    };
+    Ok(reader)
+}
+
+/// Decompress `reader` if it starts with gzip, zlib or zstd magic bytes, otherwise pass it through
+pub fn process_decompress_auto<'a, R: Read + 'a>(
+    mut reader: R,
+) -> Result<(Option<Compression>, Box<dyn Read + 'a>)> {
+    // 先读出开头几个字节判断格式，再拼回去，这样不需要 Seek
+    let mut header = Vec::with_capacity(4);
+    (&mut reader).take(4).read_to_end(&mut header)?;
+    let detected = process_detect_compression(&header);
+    let reader = Cursor::new(header).chain(reader);
+    match detected {
+        Some(compression) => Ok((detected, process_decompress_reader(reader, compression)?)),
+        None => Ok((None, Box::new(reader))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_roundtrip() -> Result<()> {
+        let data = "hello world\n".repeat(100);
+        for compression in [
+            Compression::Gzip,
+            Compression::Zlib,
+            Compression::Zstd,
+            Compression::Brotli,
+        ] {
+            let mut compressed = Vec::new();
+            process_compress_reader(data.as_bytes(), compression, None)?
+                .read_to_end(&mut compressed)?;
+            assert!(compressed.len() < data.len());
+
+            let mut ret = String::new();
+            process_decompress_reader(compressed.as_slice(), compression)?
+                .read_to_string(&mut ret)?;
+            assert_eq!(ret, data);
+
+            if compression != Compression::Brotli {
+                let (detected, mut reader) = process_decompress_auto(compressed.as_slice())?;
+                assert_eq!(detected, Some(compression));
+                let mut ret = String::new();
+                reader.read_to_string(&mut ret)?;
+                assert_eq!(ret, data);
+            }
+        }
+        assert!(process_compress_reader(data.as_bytes(), Compression::Gzip, Some(10)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompress_auto_passthrough() -> Result<()> {
+        for data in [&b"x marks the spot"[..], b"ab", b""] {
+            let (detected, mut reader) = process_decompress_auto(data)?;
+            assert_eq!(detected, None);
+            let mut ret = Vec::new();
+            reader.read_to_end(&mut ret)?;
+            assert_eq!(ret, data);
+        }
+
+        // 头部字段不对的不算：gzip 的压缩方法不是 8，zlib 的块类型是保留的 3
+        for data in [&[0x1f, 0x8b, 1, 2, 3][..], &[0x78, 0x9c, 0xff, 0x00]] {
+            assert_eq!(process_decompress_auto(data)?.0, None);
+        }
+
+        // magic bytes 对得上但数据坏了要报错，不能原样输出
+        for data in [
+            &b"x^2 + y^2 = r^2"[..],
+            &[0x1f, 0x8b, 0x08, 0x00, 0xff, 0xff],
+            &[0x28, 0xb5, 0x2f, 0xfd],
+        ] {
+            let (detected, mut reader) = process_decompress_auto(data)?;
+            assert!(detected.is_some());
+            assert!(reader.read_to_end(&mut Vec::new()).is_err());
+        }
+        Ok(())
+    }
+}
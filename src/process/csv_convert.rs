@@ -2,7 +2,7 @@ use std::fs;
 
 use crate::cli::OutputFormat;
 
-use csv::Reader;
+use csv::{Reader, Writer};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -22,6 +22,8 @@ pub fn process_csv(input: &str, output: String, format: OutputFormat) -> anyhow:
     let mut ret = Vec::with_capacity(128);
     let headers = reader.headers()?.clone();
 
+    let mut records = Vec::with_capacity(128);
+
     for result in reader.records() {
         let record = result?;
         // headers.iter() -> 使用 headers 的迭代器
@@ -34,9 +36,18 @@ pub fn process_csv(input: &str, output: String, format: OutputFormat) -> anyhow:
             .zip(record.iter())
             .collect::<serde_json::Value>();
         ret.push(json_value);
+        records.push(record);
     }
 
     let content = match format {
+        OutputFormat::Csv => {
+            let mut writer = Writer::from_writer(Vec::new());
+            writer.write_record(&headers)?;
+            for record in &records {
+                writer.write_record(record)?;
+            }
+            String::from_utf8(writer.into_inner()?)?
+        }
         OutputFormat::Json => serde_json::to_string_pretty(&ret)?,
         OutputFormat::Yaml => serde_yaml::to_string(&ret)?,
     };
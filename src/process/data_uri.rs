@@ -0,0 +1,118 @@
+use std::path::Path;
+
+use anyhow::Result;
+use base64::Engine as _;
+use mime::Mime;
+use percent_encoding::percent_decode_str;
+
+use crate::Base64Format;
+
+/// Media type and payload of a parsed `data:` URI
+#[derive(Debug)]
+pub struct DataUri {
+    pub mime: Mime,
+    pub data: Vec<u8>,
+}
+
+/// MIME type from the magic bytes of `data`, falling back to the extension of `path`
+pub fn process_guess_mime(data: &[u8], path: Option<&str>) -> String {
+    if let Some(kind) = infer::get(data) {
+        return kind.mime_type().to_string();
+    }
+    // svg、css 这类文本格式没有 magic bytes，只能看扩展名
+    if let Some(mime) = path.and_then(|p| mime_guess::from_path(Path::new(p)).first_raw()) {
+        return mime.to_string();
+    }
+    match std::str::from_utf8(data) {
+        Ok(_) => "text/plain".to_string(),
+        Err(_) => "application/octet-stream".to_string(),
+    }
+}
+
+pub fn process_data_uri_encode(data: &[u8], mime: &str) -> Result<String> {
+    let mime: Mime = mime
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid media type: {}", mime))?;
+    let encoded = Base64Format::Standard.engine(None).encode(data);
+    Ok(format!("data:{};base64,{}", mime, encoded))
+}
+
+/// Parse `data:[<media type>][;base64],<data>`, the payload is percent-encoded unless `;base64` is given
+pub fn process_data_uri_decode(uri: &str) -> Result<DataUri> {
+    let uri = uri.trim();
+    let rest = match uri.get(..5) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("data:") => &uri[5..],
+        _ => anyhow::bail!("Not a data URI"),
+    };
+    let Some((meta, payload)) = rest.split_once(',') else {
+        anyhow::bail!("Data URI is missing the ',' before the payload");
+    };
+
+    let base64_at = meta.len().saturating_sub(7);
+    let (media_type, is_base64) = match meta.get(base64_at..) {
+        Some(suffix) if suffix.eq_ignore_ascii_case(";base64") => (&meta[..base64_at], true),
+        _ => (meta, false),
+    };
+    // RFC 2397：省略媒体类型时默认是 text/plain;charset=US-ASCII
+    let mime = match media_type {
+        "" => mime::TEXT_PLAIN,
+        m if m.starts_with(';') => format!("text/plain{}", m)
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid media type: {}", m))?,
+        m => m
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid media type: {}", m))?,
+    };
+
+    let payload: Vec<u8> = percent_decode_str(payload).collect();
+    let data = if is_base64 {
+        let payload: Vec<u8> = payload
+            .into_iter()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect();
+        Base64Format::Standard.engine(None).decode(payload)?
+    } else {
+        payload
+    };
+    Ok(DataUri { mime, data })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_uri_roundtrip() -> Result<()> {
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR";
+        let mime = process_guess_mime(png, Some("logo.jpg"));
+        assert_eq!(mime, "image/png");
+
+        let uri = process_data_uri_encode(png, &mime)?;
+        assert!(uri.starts_with("data:image/png;base64,iVBORw0KGgo"));
+        let parsed = process_data_uri_decode(&uri)?;
+        assert_eq!(parsed.mime.essence_str(), "image/png");
+        assert_eq!(parsed.data, png);
+        Ok(())
+    }
+
+    #[test]
+    fn test_data_uri_decode() -> Result<()> {
+        let parsed = process_data_uri_decode("data:,Hello%2C%20World%21")?;
+        assert_eq!(parsed.mime, mime::TEXT_PLAIN);
+        assert_eq!(parsed.data, b"Hello, World!");
+
+        let parsed = process_data_uri_decode("DATA:text/html;charset=utf-8;BASE64,PGI+aGk8L2I+")?;
+        assert_eq!(parsed.mime.essence_str(), "text/html");
+        assert_eq!(parsed.data, b"<b>hi</b>");
+
+        assert!(process_data_uri_decode("data:image png;base64,AAAA").is_err());
+        assert!(process_data_uri_decode("http://example.com").is_err());
+
+        assert_eq!(process_guess_mime(b"a {}", Some("site.css")), "text/css");
+        assert_eq!(
+            process_guess_mime(b"\xff\xfe", None),
+            "application/octet-stream"
+        );
+        Ok(())
+    }
+}
@@ -1,9 +1,171 @@
+use std::{collections::HashSet, fs};
+
 use rand::seq::SliceRandom;
 
 const UPPER: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
 const LOWER: &[u8] = b"abcdefghijkmnopqrstuvwxyz";
 const NUMBER: &[u8] = b"123456789";
 const SYMBOL: &[u8] = b"!@#$%^&*_";
+/// 默认字母表里去掉的容易看混的字符，`--ambiguous` 时加回来
+const AMBIGUOUS_UPPER: &[u8] = b"IO";
+const AMBIGUOUS_LOWER: &[u8] = b"l";
+const AMBIGUOUS_NUMBER: &[u8] = b"0";
+const WORDLIST: &str = include_str!("../../assets/wordlist.txt");
+/// How many candidates the `process_genpass_*` filters try before giving up
+pub(crate) const MAX_ATTEMPTS: usize = 100;
+
+/// Options for random character passwords
+#[derive(Debug, Clone)]
+pub struct GenPassOptions {
+    pub length: u8,
+    pub upper: bool,
+    pub lower: bool,
+    pub number: bool,
+    pub symbol: bool,
+    /// Minimum count of each class, `None` means one if the class is enabled
+    pub min_upper: Option<u8>,
+    pub min_lower: Option<u8>,
+    pub min_number: Option<u8>,
+    pub min_symbol: Option<u8>,
+    /// Custom alphabet replacing the built-in classes
+    pub charset: Option<String>,
+    /// Characters that must never appear
+    pub exclude: String,
+    /// Include look-alike characters such as `0`/`O` and `l`/`I`
+    pub ambiguous: bool,
+}
+
+/// A class of characters and how many of them the password needs at least
+#[derive(Debug, Clone)]
+pub struct CharClass {
+    pub name: &'static str,
+    pub chars: Vec<u8>,
+    pub min: usize,
+}
+
+impl Default for GenPassOptions {
+    fn default() -> Self {
+        Self {
+            length: 16,
+            upper: true,
+            lower: true,
+            number: true,
+            symbol: true,
+            min_upper: None,
+            min_lower: None,
+            min_number: None,
+            min_symbol: None,
+            charset: None,
+            exclude: String::new(),
+            ambiguous: false,
+        }
+    }
+}
+
+impl GenPassOptions {
+    pub fn new(length: u8, upper: bool, lower: bool, number: bool, symbol: bool) -> Self {
+        Self {
+            length,
+            upper,
+            lower,
+            number,
+            symbol,
+            ..Default::default()
+        }
+    }
+
+    /// Resolve the enabled character classes after applying charset, ambiguity and exclusions
+    pub fn classes(&self) -> anyhow::Result<Vec<CharClass>> {
+        if let Some(charset) = &self.charset {
+            if !charset.bytes().all(|c| c.is_ascii_graphic()) {
+                return Err(anyhow::anyhow!(
+                    "Charset must only contain printable ASCII characters"
+                ));
+            }
+        }
+
+        let specs = [
+            (
+                "uppercase",
+                self.upper,
+                self.min_upper,
+                UPPER,
+                AMBIGUOUS_UPPER,
+            ),
+            (
+                "lowercase",
+                self.lower,
+                self.min_lower,
+                LOWER,
+                AMBIGUOUS_LOWER,
+            ),
+            (
+                "number",
+                self.number,
+                self.min_number,
+                NUMBER,
+                AMBIGUOUS_NUMBER,
+            ),
+            ("symbol", self.symbol, self.min_symbol, SYMBOL, &[][..]),
+        ];
+
+        let mut classes = Vec::new();
+        for (name, enabled, min, builtin, ambiguous) in specs {
+            if !enabled {
+                if min.unwrap_or(0) > 0 {
+                    return Err(anyhow::anyhow!(
+                        "Minimum {} count set but {} is disabled",
+                        name,
+                        name
+                    ));
+                }
+                continue;
+            }
+
+            // 自定义字母表时按字符类型拆分成几类，这样最少数量的限制仍然有效
+            let mut chars: Vec<u8> = match &self.charset {
+                Some(charset) => charset.bytes().filter(|c| class_of(*c) == name).collect(),
+                None => builtin
+                    .iter()
+                    .chain(if self.ambiguous { ambiguous } else { &[] })
+                    .copied()
+                    .collect(),
+            };
+            chars.retain(|c| !self.exclude.as_bytes().contains(c));
+            chars.sort_unstable();
+            chars.dedup();
+
+            if chars.is_empty() {
+                // charset 里没有这一类字符时直接跳过，除非显式要求了最少数量
+                if self.charset.is_some() && matches!(min, None | Some(0)) {
+                    continue;
+                }
+                return Err(anyhow::anyhow!(
+                    "No {} characters left to choose from",
+                    name
+                ));
+            }
+            let min = min.map(|m| m as usize).unwrap_or(1);
+            classes.push(CharClass { name, chars, min });
+        }
+
+        if classes.is_empty() {
+            return Err(anyhow::anyhow!(
+                "At least one character class must be enabled"
+            ));
+        }
+        Ok(classes)
+    }
+}
+
+pub(crate) fn class_of(c: u8) -> &'static str {
+    match c {
+        b'A'..=b'Z' => "uppercase",
+        b'a'..=b'z' => "lowercase",
+        b'0'..=b'9' => "number",
+        _ => "symbol",
+    }
+}
 
 pub fn process_genpass(
     length: u8,
@@ -12,40 +174,224 @@ pub fn process_genpass(
     number: bool,
     symbol: bool,
 ) -> anyhow::Result<String> {
+    process_genpass_with(&GenPassOptions::new(length, upper, lower, number, symbol))
+}
+
+pub fn process_genpass_with(opts: &GenPassOptions) -> anyhow::Result<String> {
+    let classes = opts.classes()?;
     let mut rng = rand::thread_rng();
     let mut password = Vec::new();
     let mut chars = Vec::new();
 
-    if upper {
-        chars.extend_from_slice(UPPER);
-        password.push(*UPPER.choose(&mut rng).expect("Upper won't be empty"));
+    for class in &classes {
+        chars.extend_from_slice(&class.chars);
+        for _ in 0..class.min {
+            password.push(*class.chars.choose(&mut rng).expect("Class won't be empty"));
+        }
     }
 
-    if lower {
-        chars.extend_from_slice(LOWER);
-        password.push(*LOWER.choose(&mut rng).expect("Lower won't be empty"));
+    let length = opts.length as usize;
+    if length < password.len() {
+        return Err(anyhow::anyhow!("Length is too short"));
     }
 
-    if number {
-        chars.extend_from_slice(NUMBER);
-        password.push(*NUMBER.choose(&mut rng).expect("Number won't be empty"));
+    for _ in 0..(length - password.len()) {
+        let c = chars.choose(&mut rng).expect("Chars won't be empty");
+        password.push(*c);
     }
 
+    password.shuffle(&mut rng);
+
+    Ok(String::from_utf8(password)?)
+}
+
+/// Call `attempt` until it returns `Some`, `None` if it didn't after `max_attempts` tries
+pub(crate) fn retry<T>(
+    max_attempts: usize,
+    mut attempt: impl FnMut() -> anyhow::Result<Option<T>>,
+) -> anyhow::Result<Option<T>> {
+    for _ in 0..max_attempts {
+        if let Some(value) = attempt()? {
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+/// Generate `count` passwords with `generate`, regenerating duplicates so the batch is unique
+pub fn process_genpass_batch<T>(
+    count: usize,
+    mut generate: impl FnMut() -> anyhow::Result<(String, T)>,
+) -> anyhow::Result<Vec<(String, T)>> {
+    let mut seen = HashSet::with_capacity(count);
+    let mut ret = Vec::with_capacity(count);
+    // 字母表很小或长度很短时可能根本凑不出这么多不重复的密码，限制一下总尝试次数
+    let max_attempts = count.saturating_mul(10).max(100);
+
+    retry(max_attempts, || {
+        if ret.len() < count {
+            let (password, extra) = generate()?;
+            if seen.insert(password.clone()) {
+                ret.push((password, extra));
+            }
+        }
+        Ok((ret.len() == count).then_some(()))
+    })?;
+
+    if ret.len() < count {
+        return Err(anyhow::anyhow!(
+            "Only generated {} unique passwords out of {}, try a longer length",
+            ret.len(),
+            count
+        ));
+    }
+    Ok(ret)
+}
+
+/// Generate a diceware-style passphrase, returns the passphrase and its entropy in bits
+pub fn process_passphrase(
+    words: u8,
+    separator: &str,
+    capitalize: bool,
+    digit: bool,
+    symbol: bool,
+    wordlist: Option<&str>,
+) -> anyhow::Result<(String, f64)> {
+    if words == 0 {
+        return Err(anyhow::anyhow!("Passphrase needs at least one word"));
+    }
+
+    let content = match wordlist {
+        Some(path) => fs::read_to_string(path)?,
+        None => WORDLIST.to_string(),
+    };
+    let list = parse_wordlist(&content);
+    if list.len() < 2 {
+        return Err(anyhow::anyhow!(
+            "Wordlist needs at least two distinct words"
+        ));
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut phrase = (0..words)
+        .map(|_| {
+            let word = *list.choose(&mut rng).expect("Wordlist won't be empty");
+            if capitalize {
+                capitalize_word(word)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(separator);
+
+    // 每个词是从整个词表里均匀选的，所以熵就是 words * log2(词表大小)，大写不增加熵
+    let mut entropy = words as f64 * (list.len() as f64).log2();
+    if digit {
+        phrase.push(*NUMBER.choose(&mut rng).expect("Number won't be empty") as char);
+        entropy += (NUMBER.len() as f64).log2();
+    }
     if symbol {
-        chars.extend_from_slice(SYMBOL);
-        password.push(*SYMBOL.choose(&mut rng).expect("Symbol won't be empty"));
+        phrase.push(*SYMBOL.choose(&mut rng).expect("Symbol won't be empty") as char);
+        entropy += (SYMBOL.len() as f64).log2();
     }
 
-    if length < password.len() as u8 {
-        return Err(anyhow::anyhow!("Length is too short"));
+    Ok((phrase, entropy))
+}
+
+/// 每行取最后一列，这样 EFF 词表 `11111\tabacus` 这种带骰子编号的格式也能直接用
+fn parse_wordlist(content: &str) -> Vec<&str> {
+    let mut list: Vec<&str> = content
+        .lines()
+        .filter_map(|line| line.split_whitespace().last())
+        .collect();
+    list.sort_unstable();
+    list.dedup();
+    list
+}
+
+fn capitalize_word(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
     }
+}
 
-    for _ in 0..(length - password.len() as u8) {
-        let c = chars.choose(&mut rng).expect("Chars won't be empty");
-        password.push(*c);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_genpass_options() -> anyhow::Result<()> {
+        let opts = GenPassOptions {
+            length: 12,
+            symbol: false,
+            min_number: Some(3),
+            exclude: "aeiou".into(),
+            ..Default::default()
+        };
+        let password = process_genpass_with(&opts)?;
+        assert_eq!(password.len(), 12);
+        assert!(password.bytes().filter(u8::is_ascii_digit).count() >= 3);
+        assert!(password.bytes().all(|c| c.is_ascii_alphanumeric()));
+        assert!(!password.contains(['a', 'e', 'i', 'o', 'u']));
+
+        let opts = GenPassOptions {
+            charset: Some("ab#".into()),
+            ..Default::default()
+        };
+        let password = process_genpass_with(&opts)?;
+        assert!(password.bytes().all(|c| b"ab#".contains(&c)));
+        assert!(password.contains('#'));
+
+        // charset 里没有数字，显式要求至少一个数字时报错而不是跳过
+        let opts = GenPassOptions {
+            charset: Some("ab#".into()),
+            min_number: Some(1),
+            ..Default::default()
+        };
+        assert!(process_genpass_with(&opts).is_err());
+
+        let opts = GenPassOptions {
+            number: false,
+            min_number: Some(2),
+            ..Default::default()
+        };
+        assert!(process_genpass_with(&opts).is_err());
+        Ok(())
     }
 
-    password.shuffle(&mut rng);
+    #[test]
+    fn test_process_passphrase() -> anyhow::Result<()> {
+        let (phrase, entropy) = process_passphrase(6, "-", true, true, true, None)?;
+        let parts: Vec<&str> = phrase.split('-').collect();
+        assert_eq!(parts.len(), 6);
+        assert!(parts.iter().all(|w| w.starts_with(char::is_uppercase)));
+        // EFF 大词表 7776 个词，每个词约 12.9 bits，加上数字和符号
+        assert_eq!(parse_wordlist(WORDLIST).len(), 7776);
+        assert!((entropy - (6.0 * 7776f64.log2() + 2.0 * 9f64.log2())).abs() < 1e-9);
+        assert!(entropy > 83.0);
+        Ok(())
+    }
 
-    Ok(String::from_utf8(password)?)
+    #[test]
+    fn test_genpass_batch_is_unique() -> anyhow::Result<()> {
+        let opts = GenPassOptions {
+            length: 2,
+            charset: Some("aB".into()),
+            ..Default::default()
+        };
+        let passwords = process_genpass_batch(2, || Ok((process_genpass_with(&opts)?, ())))?;
+        assert_ne!(passwords[0].0, passwords[1].0);
+        // 每类至少一个字符，只有 "aB" 和 "Ba" 两种组合
+        assert!(process_genpass_batch(3, || Ok((process_genpass_with(&opts)?, ()))).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_eff_wordlist() {
+        let list = parse_wordlist("11111\tabacus\n11112\tabdomen\n\n11113\tabacus\n");
+        assert_eq!(list, vec!["abacus", "abdomen"]);
+    }
 }
@@ -0,0 +1,213 @@
+use std::{thread, time::Duration};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use ulid::{Generator, Ulid};
+use uuid::Uuid;
+
+use crate::UuidVersion;
+
+/// Twitter's snowflake epoch, 2010-11-04T01:42:54.657Z
+pub const SNOWFLAKE_EPOCH: u64 = 1288834974657;
+
+const SNOWFLAKE_WORKER_BITS: u64 = 10;
+const SNOWFLAKE_SEQUENCE_BITS: u64 = 12;
+
+/// Timestamp and other fields embedded in an id
+#[derive(Debug, PartialEq)]
+pub struct IdInfo {
+    pub kind: &'static str,
+    pub timestamp: DateTime<Utc>,
+    /// Snowflake worker id and sequence
+    pub snowflake: Option<(u64, u64)>,
+}
+
+/// Twitter style 64 bit id: 41 bit milliseconds since `epoch`, 10 bit worker id, 12 bit sequence
+pub struct SnowflakeGenerator {
+    epoch: u64,
+    worker_id: u64,
+    last_ms: u64,
+    sequence: u64,
+}
+
+impl SnowflakeGenerator {
+    pub fn new(worker_id: u64, epoch: u64) -> Result<Self> {
+        if worker_id >= 1 << SNOWFLAKE_WORKER_BITS {
+            anyhow::bail!("Worker id must be less than {}", 1 << SNOWFLAKE_WORKER_BITS);
+        }
+        Ok(Self {
+            epoch,
+            worker_id,
+            last_ms: 0,
+            sequence: 0,
+        })
+    }
+
+    pub fn next_id(&mut self) -> Result<u64> {
+        let mut ms = self.elapsed_ms()?;
+        if ms == self.last_ms {
+            self.sequence = (self.sequence + 1) & ((1 << SNOWFLAKE_SEQUENCE_BITS) - 1);
+            if self.sequence == 0 {
+                // 同一毫秒内 sequence 用完了，等到下一毫秒
+                while ms <= self.last_ms {
+                    thread::sleep(Duration::from_micros(100));
+                    ms = self.elapsed_ms()?;
+                }
+            }
+        } else {
+            self.sequence = 0;
+        }
+        self.last_ms = ms;
+
+        Ok((ms << (SNOWFLAKE_WORKER_BITS + SNOWFLAKE_SEQUENCE_BITS))
+            | (self.worker_id << SNOWFLAKE_SEQUENCE_BITS)
+            | self.sequence)
+    }
+
+    fn elapsed_ms(&self) -> Result<u64> {
+        let now = Utc::now().timestamp_millis() as u64;
+        match now.checked_sub(self.epoch) {
+            // 时钟回拨时不能生成可能重复的 id
+            Some(ms) if ms >= self.last_ms => Ok(ms),
+            Some(_) => anyhow::bail!("Clock moved backwards"),
+            None => anyhow::bail!("Snowflake epoch is in the future"),
+        }
+    }
+}
+
+pub fn process_uuid(version: UuidVersion) -> String {
+    match version {
+        UuidVersion::V4 => Uuid::new_v4(),
+        UuidVersion::V7 => Uuid::now_v7(),
+    }
+    .to_string()
+}
+
+/// Next ULID from `generator`, ids from the same generator keep increasing within a millisecond
+pub fn process_ulid(generator: &mut Generator) -> Result<String> {
+    let ulid = generator
+        .generate()
+        .map_err(|e| anyhow::anyhow!("Failed to generate ULID: {}", e))?;
+    Ok(ulid.to_string())
+}
+
+/// Random id of `size` characters from `alphabet`, the URL safe nanoid alphabet by default
+pub fn process_nanoid(size: usize, alphabet: Option<&str>) -> Result<String> {
+    if size == 0 {
+        anyhow::bail!("Size must be greater than 0");
+    }
+    let Some(alphabet) = alphabet else {
+        return Ok(nanoid::nanoid!(size));
+    };
+
+    let mut chars: Vec<char> = alphabet.chars().collect();
+    chars.sort_unstable();
+    chars.dedup();
+    if chars.len() < 2 || chars.len() > 256 {
+        anyhow::bail!("Alphabet must have between 2 and 256 unique characters");
+    }
+    Ok(nanoid::format(nanoid::rngs::default, &chars, size))
+}
+
+/// Extract the timestamp of a UUIDv7, ULID or snowflake id
+pub fn process_id_decode(id: &str, snowflake_epoch: u64) -> Result<IdInfo> {
+    let id = id.trim();
+
+    if !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()) {
+        let value: u64 = id.parse()?;
+        let ms = (value >> (SNOWFLAKE_WORKER_BITS + SNOWFLAKE_SEQUENCE_BITS))
+            .checked_add(snowflake_epoch)
+            .ok_or_else(|| anyhow::anyhow!("Snowflake timestamp overflows with this epoch"))?;
+        let worker_id = (value >> SNOWFLAKE_SEQUENCE_BITS) & ((1 << SNOWFLAKE_WORKER_BITS) - 1);
+        let sequence = value & ((1 << SNOWFLAKE_SEQUENCE_BITS) - 1);
+        return Ok(IdInfo {
+            kind: "snowflake",
+            timestamp: from_millis(ms)?,
+            snowflake: Some((worker_id, sequence)),
+        });
+    }
+
+    if id.len() == 26 {
+        let ulid = Ulid::from_string(id).map_err(|e| anyhow::anyhow!("Invalid ULID: {}", e))?;
+        return Ok(IdInfo {
+            kind: "ulid",
+            timestamp: from_millis(ulid.timestamp_ms())?,
+            snowflake: None,
+        });
+    }
+
+    let uuid = Uuid::parse_str(id).map_err(|_| anyhow::anyhow!("Unknown id format"))?;
+    let (seconds, nanos) = match uuid.get_version_num() {
+        7 => uuid
+            .get_timestamp()
+            .ok_or_else(|| anyhow::anyhow!("Invalid UUIDv7"))?
+            .to_unix(),
+        n => anyhow::bail!("UUIDv{} has no embedded timestamp", n),
+    };
+    Ok(IdInfo {
+        kind: "uuid v7",
+        timestamp: DateTime::from_timestamp(seconds as i64, nanos)
+            .ok_or_else(|| anyhow::anyhow!("Timestamp out of range"))?,
+        snowflake: None,
+    })
+}
+
+fn from_millis(ms: u64) -> Result<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(ms as i64)
+        .ok_or_else(|| anyhow::anyhow!("Timestamp out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ulid_is_monotonic() -> Result<()> {
+        // 同一毫秒内生成的 ULID 也要按生成顺序排序
+        let mut generator = Generator::new();
+        let ids = (0..1000)
+            .map(|_| process_ulid(&mut generator))
+            .collect::<Result<Vec<_>>>()?;
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_id_decode() -> Result<()> {
+        let info = process_id_decode("01ARZ3NDEKTSV4RRFFQ69G5FAV", SNOWFLAKE_EPOCH)?;
+        assert_eq!(info.kind, "ulid");
+        assert_eq!(info.timestamp.timestamp_millis(), 1469922850259);
+
+        let info = process_id_decode("017f22e2-79b0-7cc3-98c4-dc0c0c07398f", SNOWFLAKE_EPOCH)?;
+        assert_eq!(info.kind, "uuid v7");
+        assert_eq!(info.timestamp.timestamp_millis(), 1645557742000);
+
+        let info = process_id_decode("1541815603606036480", SNOWFLAKE_EPOCH)?;
+        assert_eq!(info.timestamp.timestamp_millis(), 1656432460105);
+        assert_eq!(info.snowflake, Some((378, 0)));
+        assert!(process_id_decode("1541815603606036480", u64::MAX).is_err());
+
+        assert!(process_id_decode(&process_uuid(UuidVersion::V4), SNOWFLAKE_EPOCH).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_id_generate() -> Result<()> {
+        let id = process_uuid(UuidVersion::V7);
+        let info = process_id_decode(&id, SNOWFLAKE_EPOCH)?;
+        assert!((Utc::now() - info.timestamp).num_seconds() < 5);
+
+        let mut generator = SnowflakeGenerator::new(7, SNOWFLAKE_EPOCH)?;
+        let a = generator.next_id()?;
+        let b = generator.next_id()?;
+        assert!(b > a);
+        let info = process_id_decode(&b.to_string(), SNOWFLAKE_EPOCH)?;
+        assert_eq!(info.snowflake.map(|(worker, _)| worker), Some(7));
+
+        let id = process_nanoid(10, Some("ab"))?;
+        assert_eq!(id.len(), 10);
+        assert!(id.chars().all(|c| c == 'a' || c == 'b'));
+        assert!(process_nanoid(10, Some("aaa")).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use anyhow::Result;
+use argon2::{Algorithm, Argon2, Params, Version};
+
+/// Upper bounds of the Argon2id parameters, 4 GiB of memory
+pub const MAX_ARGON2_MEMORY: u32 = 4 * 1024 * 1024;
+pub const MAX_ARGON2_ITERATIONS: u32 = 64;
+pub const MAX_ARGON2_PARALLELISM: u32 = 16;
+
+/// Argon2id cost parameters for deriving a key from a password
+#[derive(Debug, Clone, Copy)]
+pub struct Argon2Params {
+    /// Memory cost in KiB
+    pub memory: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for Argon2Params {
+    fn default() -> Self {
+        Self {
+            memory: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+impl Argon2Params {
+    /// Reject parameters above the limits, checked on both encrypt and decrypt so every message we write can be read back
+    pub(crate) fn check(&self) -> Result<()> {
+        if self.memory > MAX_ARGON2_MEMORY
+            || self.iterations > MAX_ARGON2_ITERATIONS
+            || self.parallelism > MAX_ARGON2_PARALLELISM
+        {
+            anyhow::bail!(
+                "Argon2 parameters are too large, the limits are {} KiB memory, {} iterations and {} parallelism",
+                MAX_ARGON2_MEMORY,
+                MAX_ARGON2_ITERATIONS,
+                MAX_ARGON2_PARALLELISM
+            );
+        }
+        Ok(())
+    }
+
+    /// 32 byte Argon2id key derived from `secret` and `salt`
+    pub(crate) fn argon2id(&self, secret: &[u8], salt: &[u8]) -> Result<[u8; 32]> {
+        self.check()?;
+        let params = Params::new(self.memory, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| anyhow::anyhow!("Invalid Argon2 parameters: {}", e))?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(secret, salt, &mut key)
+            .map_err(|e| anyhow::anyhow!("Failed to derive key: {}", e))?;
+        Ok(key)
+    }
+}
@@ -1,16 +1,62 @@
 mod b64;
+mod breach;
+mod codec;
+mod compress;
 mod csv_convert;
+mod csv_fake;
+mod data_uri;
+mod derive;
+mod detect;
 mod gen_pass;
 mod http_serve;
+mod id;
 mod jwt;
+mod kdf;
+mod otp;
+mod pem;
+mod policy;
+mod strength;
 mod text;
+mod token;
+mod url_codec;
 
 pub use b64::{process_decode, process_encode};
-pub use csv_convert::process_csv;
-pub use gen_pass::process_genpass;
+pub use breach::{process_genpass_not_breached, BreachCorpus};
+pub use codec::{process_codec_decode, process_codec_encode, process_segwit_decode, Codec};
+pub use compress::{
+    process_compress_reader, process_decompress_auto, process_decompress_reader,
+    process_detect_compression,
+};
+pub use csv_convert::{process_csv, Player};
+pub use csv_fake::{process_csv_fake, write_fake_rows, FakeColumn, FakeGenerator, FakeSpec};
+pub use data_uri::{process_data_uri_decode, process_data_uri_encode, process_guess_mime, DataUri};
+pub use derive::process_genpass_derive;
+pub use detect::{process_detect_encoding, process_sniff_content, ContentKind, DetectCandidate};
+pub use gen_pass::{
+    process_genpass, process_genpass_batch, process_genpass_with, process_passphrase, CharClass,
+    GenPassOptions,
+};
 pub use http_serve::process_http_serve;
+pub use id::{
+    process_id_decode, process_nanoid, process_ulid, process_uuid, IdInfo, SnowflakeGenerator,
+    SNOWFLAKE_EPOCH,
+};
 pub use jwt::{process_jwt_secret_generate, process_jwt_sign, process_jwt_verify};
+pub use kdf::{Argon2Params, MAX_ARGON2_ITERATIONS, MAX_ARGON2_MEMORY, MAX_ARGON2_PARALLELISM};
+pub use otp::{
+    process_hotp, process_hotp_verify, process_otp_decode_secret, process_otp_generate_secret,
+    process_otp_uri, process_totp, process_totp_verify, OtpParams, MAX_OTP_SKEW,
+};
+pub use pem::{process_der_validate, process_pem_decode, process_pem_encode};
+pub use policy::{process_genpass_policy, PasswordPolicy};
+pub use strength::{
+    process_genpass_min_score, process_password_audit, process_password_strength, AuditEntry,
+    AuditReport, CrackTime, CrackTimes, StrengthReport,
+};
 pub use text::{
-    process_text_decrypt, process_text_encrypt, process_text_key_generate, process_text_sign,
+    process_text_decrypt, process_text_decrypt_password, process_text_encrypt,
+    process_text_encrypt_password, process_text_key_generate, process_text_sign,
     process_text_verify,
 };
+pub use token::{process_token_generate, process_token_verify};
+pub use url_codec::{process_url_decode, process_url_encode, process_url_parse, UrlParts};
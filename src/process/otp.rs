@@ -0,0 +1,256 @@
+use anyhow::Result;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::{rngs::OsRng, RngCore};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+use crate::OtpAlgorithm;
+
+/// Largest accepted `skew`, a wider window makes guessing codes easier
+pub const MAX_OTP_SKEW: u64 = 10;
+
+/// Parameters shared by the secret, its otpauth URI and every code generated from it
+#[derive(Debug, Clone, Copy)]
+pub struct OtpParams {
+    pub algorithm: OtpAlgorithm,
+    pub digits: u32,
+    /// TOTP time step in seconds
+    pub period: u64,
+}
+
+impl Default for OtpParams {
+    fn default() -> Self {
+        Self {
+            algorithm: OtpAlgorithm::Sha1,
+            digits: 6,
+            period: 30,
+        }
+    }
+}
+
+impl OtpAlgorithm {
+    fn hmac(&self, key: &[u8], msg: &[u8]) -> Vec<u8> {
+        // HMAC 可以接受任意长度的 key，new_from_slice 不会失败
+        match self {
+            OtpAlgorithm::Sha1 => {
+                let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC takes any key size");
+                mac.update(msg);
+                mac.finalize().into_bytes().to_vec()
+            }
+            OtpAlgorithm::Sha256 => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes any key size");
+                mac.update(msg);
+                mac.finalize().into_bytes().to_vec()
+            }
+            OtpAlgorithm::Sha512 => {
+                let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC takes any key size");
+                mac.update(msg);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+}
+
+/// Decode a base32 secret as shown by authenticator apps, case, spaces and padding are ignored
+pub fn process_otp_decode_secret(secret: &str) -> Result<Vec<u8>> {
+    let secret: String = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=')
+        .collect::<String>()
+        .to_uppercase();
+    let key = BASE32_NOPAD
+        .decode(secret.as_bytes())
+        .map_err(|e| anyhow::anyhow!("Invalid base32 secret: {}", e))?;
+    if key.is_empty() {
+        anyhow::bail!("Secret is empty");
+    }
+    Ok(key)
+}
+
+/// Generate a random secret of `bytes` bytes, base32 encoded without padding
+pub fn process_otp_generate_secret(bytes: usize) -> Result<String> {
+    if bytes < 16 {
+        anyhow::bail!("Secret needs at least 16 bytes");
+    }
+    let mut key = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut key);
+    Ok(BASE32_NOPAD.encode(&key))
+}
+
+/// Build the `otpauth://totp/...` URI understood by authenticator apps
+pub fn process_otp_uri(secret: &str, issuer: &str, account: &str, params: OtpParams) -> String {
+    let issuer = utf8_percent_encode(issuer, NON_ALPHANUMERIC).to_string();
+    let account = utf8_percent_encode(account, NON_ALPHANUMERIC).to_string();
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm={}&digits={}&period={}",
+        issuer,
+        account,
+        secret,
+        issuer,
+        params.algorithm.to_string().to_uppercase(),
+        params.digits,
+        params.period
+    )
+}
+
+/// RFC 4226 HOTP code for `counter`
+pub fn process_hotp(key: &[u8], counter: u64, params: OtpParams) -> Result<String> {
+    if !(6..=10).contains(&params.digits) {
+        anyhow::bail!("Digits must be between 6 and 10");
+    }
+
+    let hash = params.algorithm.hmac(key, &counter.to_be_bytes());
+    // dynamic truncation：用最后一个字节的低 4 位作为偏移取 31 位
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let code = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    let code = code as u64 % 10u64.pow(params.digits);
+    Ok(format!("{:0width$}", code, width = params.digits as usize))
+}
+
+/// RFC 6238 TOTP code at unix time `time`
+pub fn process_totp(key: &[u8], time: u64, params: OtpParams) -> Result<String> {
+    if params.period == 0 {
+        anyhow::bail!("Period must be greater than 0");
+    }
+    process_hotp(key, time / params.period, params)
+}
+
+/// Check a TOTP code within `skew` time steps around `time`, returns the matching step offset
+pub fn process_totp_verify(
+    key: &[u8],
+    code: &str,
+    time: u64,
+    skew: u64,
+    params: OtpParams,
+) -> Result<Option<i64>> {
+    if params.period == 0 {
+        anyhow::bail!("Period must be greater than 0");
+    }
+    let skew = check_skew(skew)?;
+    let step = time / params.period;
+    for offset in -skew..=skew {
+        let Some(counter) = step.checked_add_signed(offset) else {
+            continue;
+        };
+        if constant_time_eq(&process_hotp(key, counter, params)?, code) {
+            return Ok(Some(offset));
+        }
+    }
+    Ok(None)
+}
+
+fn check_skew(skew: u64) -> Result<i64> {
+    match skew {
+        0..=MAX_OTP_SKEW => Ok(i64::try_from(skew)?),
+        _ => anyhow::bail!("Skew must be at most {}", MAX_OTP_SKEW),
+    }
+}
+
+/// Check a HOTP code against `counter..=counter + skew`, returns the matching counter
+pub fn process_hotp_verify(
+    key: &[u8],
+    code: &str,
+    counter: u64,
+    skew: u64,
+    params: OtpParams,
+) -> Result<Option<u64>> {
+    // HOTP 只有客户端会往前走，所以只向后看
+    check_skew(skew)?;
+    for counter in counter..=counter.saturating_add(skew) {
+        if constant_time_eq(&process_hotp(key, counter, params)?, code) {
+            return Ok(Some(counter));
+        }
+    }
+    Ok(None)
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hotp_rfc4226() -> Result<()> {
+        let key = b"12345678901234567890";
+        let expected = ["755224", "287082", "359152", "969429", "338314"];
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(
+                process_hotp(key, counter as u64, OtpParams::default())?,
+                *code
+            );
+        }
+        assert_eq!(
+            process_hotp_verify(key, "969429", 1, 2, OtpParams::default())?,
+            Some(3)
+        );
+        assert_eq!(
+            process_hotp_verify(key, "755224", 1, 2, OtpParams::default())?,
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_totp_rfc6238() -> Result<()> {
+        let cases = [
+            (OtpAlgorithm::Sha1, &b"12345678901234567890"[..]),
+            (
+                OtpAlgorithm::Sha256,
+                &b"12345678901234567890123456789012"[..],
+            ),
+            (
+                OtpAlgorithm::Sha512,
+                &b"1234567890123456789012345678901234567890123456789012345678901234"[..],
+            ),
+        ];
+        let expected = [
+            (59, ["94287082", "46119246", "90693936"]),
+            (1111111109, ["07081804", "68084774", "25091201"]),
+            (20000000000, ["65353130", "77737706", "47863826"]),
+        ];
+        for (time, codes) in expected {
+            for ((algorithm, key), code) in cases.iter().zip(codes) {
+                let params = OtpParams {
+                    algorithm: *algorithm,
+                    digits: 8,
+                    period: 30,
+                };
+                assert_eq!(process_totp(key, time, params)?, code);
+            }
+        }
+
+        let params = OtpParams::default();
+        let key = &cases[0].1;
+        let code = process_totp(key, 59, params)?;
+        assert_eq!(process_totp_verify(key, &code, 89, 1, params)?, Some(-1));
+        assert_eq!(process_totp_verify(key, &code, 119, 1, params)?, None);
+        assert!(process_totp_verify(key, &code, 89, u64::MAX, params).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_otp_secret() -> Result<()> {
+        let secret = process_otp_generate_secret(20)?;
+        assert_eq!(secret.len(), 32);
+        assert_eq!(process_otp_decode_secret(&secret)?.len(), 20);
+        assert_eq!(
+            process_otp_decode_secret("gezd gnbv gy3t qojq")?,
+            b"1234567890"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::Result;
+use pem::{EncodeConfig, LineEnding, Pem};
+
+/// 嵌套太深的 DER 基本都是恶意构造的
+const MAX_DER_DEPTH: usize = 64;
+
+/// Check that `data` is exactly one well-formed DER element: definite, minimally encoded lengths
+/// that stay inside their parent, recursively for constructed types
+pub fn process_der_validate(data: &[u8]) -> Result<()> {
+    let rest = der_element(data, 0)?;
+    if !rest.is_empty() {
+        anyhow::bail!("{} trailing bytes after the DER element", rest.len());
+    }
+    Ok(())
+}
+
+/// Validate the element at the start of `data` and return what follows it
+fn der_element(data: &[u8], depth: usize) -> Result<&[u8]> {
+    if depth > MAX_DER_DEPTH {
+        anyhow::bail!("DER nesting deeper than {}", MAX_DER_DEPTH);
+    }
+    let Some(&tag) = data.first() else {
+        anyhow::bail!("Truncated DER: missing tag");
+    };
+    let mut pos = 1;
+    if tag & 0x1f == 0x1f {
+        // 高 tag 号：base-128，最后一个字节最高位为 0
+        loop {
+            let Some(&b) = data.get(pos) else {
+                anyhow::bail!("Truncated DER: incomplete tag number");
+            };
+            if pos == 1 && b == 0x80 {
+                anyhow::bail!("Tag number isn't minimally encoded");
+            }
+            pos += 1;
+            if b & 0x80 == 0 {
+                break;
+            }
+        }
+    }
+
+    let Some(&first) = data.get(pos) else {
+        anyhow::bail!("Truncated DER: missing length");
+    };
+    pos += 1;
+    let len = match first {
+        n if n < 0x80 => n as usize,
+        0x80 => anyhow::bail!("Indefinite length isn't allowed in DER"),
+        n => {
+            let bytes = (n & 0x7f) as usize;
+            if bytes > std::mem::size_of::<usize>() {
+                anyhow::bail!("DER length too large");
+            }
+            let Some(len_bytes) = data.get(pos..pos + bytes) else {
+                anyhow::bail!("Truncated DER: incomplete length");
+            };
+            pos += bytes;
+            if len_bytes[0] == 0 {
+                anyhow::bail!("Length isn't minimally encoded");
+            }
+            let len = len_bytes
+                .iter()
+                .fold(0usize, |acc, &b| acc << 8 | b as usize);
+            if len < 0x80 {
+                anyhow::bail!("Length isn't minimally encoded");
+            }
+            len
+        }
+    };
+
+    let Some(mut contents) = data.get(pos..).and_then(|rest| rest.get(..len)) else {
+        anyhow::bail!(
+            "Truncated DER: element needs {} bytes, {} left",
+            len,
+            data.len() - pos
+        );
+    };
+    if tag & 0x20 != 0 {
+        while !contents.is_empty() {
+            contents = der_element(contents, depth + 1)?;
+        }
+    }
+    Ok(&data[pos + len..])
+}
+
+/// Encrypted blocks (`Proc-Type: 4,ENCRYPTED`) carry ciphertext instead of DER
+fn is_encrypted(pem: &Pem) -> bool {
+    pem.headers()
+        .get("Proc-Type")
+        .is_some_and(|v| v.contains("ENCRYPTED"))
+}
+
+/// Wrap `data` in BEGIN/END `label` lines with 64 column base64 and optional RFC 1421 headers
+pub fn process_pem_encode(
+    data: &[u8],
+    label: &str,
+    headers: &[(String, String)],
+    validate: bool,
+) -> Result<String> {
+    let label = label.trim();
+    if label.starts_with('-')
+        || label.ends_with('-')
+        || !label.chars().all(|c| c.is_ascii_graphic() || c == ' ')
+    {
+        anyhow::bail!("Invalid PEM label: {:?}", label);
+    }
+
+    let mut pem = Pem::new(label, data);
+    for (key, value) in headers {
+        pem.headers_mut()
+            .add(key, value)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+    }
+    if validate && !is_encrypted(&pem) {
+        process_der_validate(data).map_err(|e| anyhow::anyhow!("Payload is not DER: {}", e))?;
+    }
+    Ok(pem::encode_config(
+        &pem,
+        EncodeConfig::new().set_line_ending(LineEnding::LF),
+    ))
+}
+
+/// Parse every PEM block in `text`, text between blocks (e.g. OpenSSL's `Bag Attributes`) is skipped
+pub fn process_pem_decode(text: &[u8], validate: bool) -> Result<Vec<Pem>> {
+    let pems = pem::parse_many(text).map_err(|e| anyhow::anyhow!("Invalid PEM: {}", e))?;
+    if pems.is_empty() {
+        anyhow::bail!("No PEM block found");
+    }
+    if validate {
+        for (i, pem) in pems.iter().enumerate() {
+            if is_encrypted(pem) {
+                continue;
+            }
+            process_der_validate(pem.contents())
+                .map_err(|e| anyhow::anyhow!("Block {} ({}) is not DER: {}", i, pem.tag(), e))?;
+        }
+    }
+    Ok(pems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 8410 里的 Ed25519 示例公钥
+    const ED25519_PUBLIC: &str = "-----BEGIN PUBLIC KEY-----
+MCowBQYDK2VwAyEAGb9ECWmEzf6FQbrBZ9w7lshQhqowtrbLDFw4rXAxZuE=
+-----END PUBLIC KEY-----
+";
+
+    #[test]
+    fn test_pem_roundtrip() -> Result<()> {
+        let pems = process_pem_decode(ED25519_PUBLIC.as_bytes(), true)?;
+        assert_eq!(pems.len(), 1);
+        assert_eq!(pems[0].tag(), "PUBLIC KEY");
+        assert_eq!(pems[0].contents().len(), 44);
+
+        let headers = vec![("Comment".to_string(), "test key".to_string())];
+        let encoded = process_pem_encode(pems[0].contents(), "PUBLIC KEY", &headers, true)?;
+        assert!(encoded.starts_with("-----BEGIN PUBLIC KEY-----\nComment: test key\n\n"));
+
+        let bundle = format!("{}junk between blocks\n{}", encoded, ED25519_PUBLIC);
+        let pems = process_pem_decode(bundle.as_bytes(), true)?;
+        assert_eq!(pems.len(), 2);
+        assert_eq!(pems[0].headers().get("Comment"), Some("test key"));
+        assert_eq!(pems[0].contents(), pems[1].contents());
+
+        assert!(process_pem_encode(b"raw key bytes", "PUBLIC KEY", &[], true).is_err());
+        assert!(process_pem_encode(b"raw key bytes", "PUBLIC KEY", &[], false).is_ok());
+        assert!(process_pem_decode(b"no pem here", true).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_der_validate() {
+        assert!(process_der_validate(b"\x30\x03\x02\x01\x05").is_ok());
+        // 截断、多余字节、不定长、非最短长度编码
+        assert!(process_der_validate(b"\x30\x05\x02\x01").is_err());
+        assert!(process_der_validate(b"\x02\x01\x05\x00").is_err());
+        assert!(process_der_validate(b"\x30\x80\x02\x01\x05\x00\x00").is_err());
+        assert!(process_der_validate(b"\x04\x81\x01\x00").is_err());
+        // SEQUENCE 里的元素越界
+        assert!(process_der_validate(b"\x30\x03\x02\x05\x05").is_err());
+    }
+}
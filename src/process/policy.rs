@@ -0,0 +1,262 @@
+use std::fs;
+
+use anyhow::Result;
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+
+use crate::GenPassOptions;
+
+use super::gen_pass::{class_of, retry, MAX_ATTEMPTS};
+
+/// Password rules of a target system, loaded from a toml file
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PasswordPolicy {
+    pub min_length: Option<u8>,
+    pub max_length: Option<u8>,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_number: bool,
+    pub require_symbol: bool,
+    /// The only symbols the system accepts, all other symbols are forbidden
+    pub allowed_symbols: Option<String>,
+    /// Characters that must never appear
+    pub forbidden: String,
+    /// Forbid the same character twice in a row, e.g. `aa`
+    pub no_repeat: bool,
+    /// Forbid ascending or descending runs of this length, e.g. 3 forbids `abc` and `321`
+    pub no_sequence: Option<u8>,
+    pub start_with_letter: bool,
+}
+
+impl PasswordPolicy {
+    pub fn load(path: &str) -> Result<Self> {
+        let policy: Self = toml::from_str(&fs::read_to_string(path)?)?;
+        if let (Some(min), Some(max)) = (policy.min_length, policy.max_length) {
+            if min > max {
+                anyhow::bail!("Invalid policy: min_length {} > max_length {}", min, max);
+            }
+        }
+        Ok(policy)
+    }
+
+    /// Check a password against the policy and return every rule it violates
+    pub fn check(&self, password: &str) -> Vec<String> {
+        let mut violations = Vec::new();
+        let chars: Vec<char> = password.chars().collect();
+        let len = chars.len();
+
+        if let Some(min) = self.min_length {
+            if len < min as usize {
+                violations.push(format!("shorter than {} characters", min));
+            }
+        }
+        if let Some(max) = self.max_length {
+            if len > max as usize {
+                violations.push(format!("longer than {} characters", max));
+            }
+        }
+
+        let requirements = [
+            (self.require_uppercase, "uppercase"),
+            (self.require_lowercase, "lowercase"),
+            (self.require_number, "number"),
+            (self.require_symbol, "symbol"),
+        ];
+        for (required, name) in requirements {
+            if required && !chars.iter().any(|c| char_class(*c) == name) {
+                violations.push(format!("no {} character", name));
+            }
+        }
+
+        if let Some(c) = chars.iter().find(|c| self.forbidden.contains(**c)) {
+            violations.push(format!("contains forbidden character {:?}", c));
+        }
+        if let Some(allowed) = &self.allowed_symbols {
+            if let Some(c) = chars
+                .iter()
+                .find(|c| char_class(**c) == "symbol" && !allowed.contains(**c))
+            {
+                violations.push(format!("contains symbol {:?} which is not allowed", c));
+            }
+        }
+
+        if self.no_repeat {
+            if let Some(w) = chars.windows(2).find(|w| w[0] == w[1]) {
+                violations.push(format!("repeats {:?}", w[0]));
+            }
+        }
+
+        if let Some(run) = self.no_sequence {
+            let run = run.max(2) as usize;
+            if let Some(w) = chars.windows(run).find(|w| is_sequence(w)) {
+                violations.push(format!(
+                    "contains sequence {:?}",
+                    w.iter().collect::<String>()
+                ));
+            }
+        }
+
+        if self.start_with_letter && !chars.first().is_some_and(|c| c.is_ascii_alphabetic()) {
+            violations.push("doesn't start with a letter".into());
+        }
+
+        violations
+    }
+
+    /// Password length to generate: the requested length clamped into the policy's range
+    fn length(&self, length: u8) -> u8 {
+        let length = self.max_length.map_or(length, |max| length.min(max));
+        self.min_length.map_or(length, |min| length.max(min))
+    }
+
+    /// Apply the policy's character restrictions on top of the generator options
+    fn options(&self, opts: &GenPassOptions) -> Result<GenPassOptions> {
+        let mut opts = opts.clone();
+        opts.length = self.length(opts.length);
+        opts.exclude.push_str(&self.forbidden);
+
+        if let Some(allowed) = &self.allowed_symbols {
+            // 用户可能允许内置符号表之外的符号，这里直接把字母表换成 "非符号类 + 允许的符号"
+            let mut charset: String = opts
+                .classes()?
+                .iter()
+                .filter(|c| c.name != "symbol")
+                .flat_map(|c| c.chars.iter().map(|b| *b as char))
+                .collect();
+            if opts.symbol {
+                charset.extend(allowed.chars().filter(|c| !opts.exclude.contains(*c)));
+            }
+            opts.charset = Some(charset);
+        }
+
+        let requirements = [
+            (
+                self.require_uppercase,
+                &mut opts.upper,
+                &mut opts.min_upper,
+                "uppercase",
+            ),
+            (
+                self.require_lowercase,
+                &mut opts.lower,
+                &mut opts.min_lower,
+                "lowercase",
+            ),
+            (
+                self.require_number,
+                &mut opts.number,
+                &mut opts.min_number,
+                "number",
+            ),
+            (
+                self.require_symbol,
+                &mut opts.symbol,
+                &mut opts.min_symbol,
+                "symbol",
+            ),
+        ];
+        for (required, enabled, min, name) in requirements {
+            if required {
+                if !*enabled {
+                    anyhow::bail!("Policy requires {} characters but they are disabled", name);
+                }
+                *min = Some(min.unwrap_or(1).max(1));
+            }
+        }
+        Ok(opts)
+    }
+}
+
+fn char_class(c: char) -> &'static str {
+    if c.is_ascii() {
+        class_of(c as u8)
+    } else {
+        "symbol"
+    }
+}
+
+fn is_sequence(w: &[char]) -> bool {
+    if !w.iter().all(|c| c.is_ascii_alphanumeric()) {
+        return false;
+    }
+    let w: Vec<i32> = w.iter().map(|c| c.to_ascii_lowercase() as i32).collect();
+    let step = w[1] - w[0];
+    (step == 1 || step == -1) && w.windows(2).all(|p| p[1] - p[0] == step)
+}
+
+/// Generate a password that complies with the policy
+///
+/// 每次都从整个字母表里均匀随机出一整个密码，不合规就整个丢掉重来。这样结果在所有合规密码里是均匀分布的，
+/// 不像 "先放一个字母在开头再打乱" 这种做法会让某些位置的字符分布有偏差
+pub fn process_genpass_policy(opts: &GenPassOptions, policy: &PasswordPolicy) -> Result<String> {
+    let opts = policy.options(opts)?;
+    let classes = opts.classes()?;
+    let alphabet: Vec<u8> = classes.iter().flat_map(|c| c.chars.clone()).collect();
+    let mut rng = rand::thread_rng();
+
+    // 整个丢掉重来的概率比较大，单次尝试又很便宜，所以多试一些
+    let max_attempts = MAX_ATTEMPTS * 1000;
+    retry(max_attempts, || {
+        let password: Vec<u8> = (0..opts.length)
+            .map(|_| *alphabet.choose(&mut rng).expect("Alphabet won't be empty"))
+            .collect();
+        let enough = classes
+            .iter()
+            .all(|class| password.iter().filter(|c| class.chars.contains(c)).count() >= class.min);
+        if !enough {
+            return Ok(None);
+        }
+
+        let password = String::from_utf8(password)?;
+        Ok(policy.check(&password).is_empty().then_some(password))
+    })?
+    .ok_or_else(|| {
+        anyhow::anyhow!(
+            "Failed to generate a compliant password after {} attempts, the policy may be too strict",
+            max_attempts
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"
+min_length = 12
+max_length = 30
+require_uppercase = true
+require_lowercase = true
+require_number = true
+allowed_symbols = "_$#"
+no_repeat = true
+no_sequence = 3
+start_with_letter = true
+"#;
+
+    #[test]
+    fn test_policy_check() -> Result<()> {
+        let policy: PasswordPolicy = toml::from_str(POLICY)?;
+        assert!(policy.check("Kx7_mq9$Lpw2").is_empty());
+
+        let violations = policy.check("1abcdd!");
+        assert_eq!(violations.len(), 6, "{:?}", violations);
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_policy() -> Result<()> {
+        let policy: PasswordPolicy = toml::from_str(POLICY)?;
+        let opts = GenPassOptions {
+            length: 8,
+            ..Default::default()
+        };
+        for _ in 0..20 {
+            let password = process_genpass_policy(&opts, &policy)?;
+            assert_eq!(password.len(), 12);
+            assert!(policy.check(&password).is_empty(), "{}", password);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use std::io::{BufRead, BufReader, Read};
+
+use anyhow::Result;
+use serde::Serialize;
+use zxcvbn::{time_estimates::CrackTimeSeconds, zxcvbn};
+
+use super::gen_pass::{retry, MAX_ATTEMPTS};
+use crate::BreachCorpus;
+
+/// zxcvbn estimate of a password in a serializable form
+#[derive(Debug, Clone, Serialize)]
+pub struct StrengthReport {
+    pub score: u8,
+    pub guesses_log10: f64,
+    pub crack_times: CrackTimes,
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CrackTimes {
+    pub online_throttling_100_per_hour: CrackTime,
+    pub online_no_throttling_10_per_second: CrackTime,
+    pub offline_slow_hashing_1e4_per_second: CrackTime,
+    pub offline_fast_hashing_1e10_per_second: CrackTime,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CrackTime {
+    pub seconds: f64,
+    pub display: String,
+}
+
+impl From<CrackTimeSeconds> for CrackTime {
+    fn from(value: CrackTimeSeconds) -> Self {
+        let seconds = match value {
+            CrackTimeSeconds::Integer(i) => i as f64,
+            CrackTimeSeconds::Float(f) => f,
+        };
+        Self {
+            seconds,
+            display: value.to_string(),
+        }
+    }
+}
+
+pub fn process_password_strength(password: &str, user_inputs: &[&str]) -> Result<StrengthReport> {
+    let estimate = zxcvbn(password, user_inputs)?;
+    let crack_times = estimate.crack_times();
+    let (warning, suggestions) = match estimate.feedback() {
+        Some(feedback) => (
+            feedback.warning().map(|w| w.to_string()),
+            feedback
+                .suggestions()
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        ),
+        None => (None, Vec::new()),
+    };
+
+    Ok(StrengthReport {
+        score: estimate.score(),
+        guesses_log10: estimate.guesses_log10(),
+        crack_times: CrackTimes {
+            online_throttling_100_per_hour: crack_times.online_throttling_100_per_hour().into(),
+            online_no_throttling_10_per_second: crack_times
+                .online_no_throttling_10_per_second()
+                .into(),
+            offline_slow_hashing_1e4_per_second: crack_times
+                .offline_slow_hashing_1e4_per_second()
+                .into(),
+            offline_fast_hashing_1e10_per_second: crack_times
+                .offline_fast_hashing_1e10_per_second()
+                .into(),
+        },
+        warning,
+        suggestions,
+    })
+}
+
+/// Strength of one audited password, identified by its line number so the password itself is never shown
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub line: usize,
+    #[serde(flatten)]
+    pub strength: StrengthReport,
+    /// Times the password appears in the breach corpus, only set when a corpus is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breached: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditReport {
+    pub entries: Vec<AuditEntry>,
+    /// Number of passwords per zxcvbn score, index is the score
+    pub histogram: [usize; 5],
+}
+
+impl AuditReport {
+    /// Number of audited passwords with a score lower than `score`
+    pub fn count_below(&self, score: u8) -> usize {
+        self.histogram[..score as usize].iter().sum()
+    }
+
+    /// Number of audited passwords found in the breach corpus
+    pub fn count_breached(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| e.breached.unwrap_or(0) > 0)
+            .count()
+    }
+}
+
+/// Audit passwords read one per line, empty lines are skipped
+pub fn process_password_audit(
+    reader: &mut dyn Read,
+    user_inputs: &[&str],
+    mut breach: Option<&mut BreachCorpus>,
+) -> Result<AuditReport> {
+    let mut entries = Vec::new();
+    let mut histogram = [0; 5];
+
+    for (i, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        let password = line.trim_end_matches('\r');
+        if password.is_empty() {
+            continue;
+        }
+
+        let strength = process_password_strength(password, user_inputs)?;
+        histogram[strength.score as usize] += 1;
+        let breached = match breach.as_deref_mut() {
+            Some(corpus) => Some(corpus.count(password)?),
+            None => None,
+        };
+        entries.push(AuditEntry {
+            line: i + 1,
+            strength,
+            breached,
+        });
+    }
+
+    Ok(AuditReport { entries, histogram })
+}
+
+/// Call `generate` until the candidate reaches `min_score`, giving up after a bounded number of attempts
+pub fn process_genpass_min_score(
+    min_score: u8,
+    mut generate: impl FnMut() -> Result<String>,
+) -> Result<(String, StrengthReport)> {
+    retry(MAX_ATTEMPTS, || {
+        let password = generate()?;
+        let report = process_password_strength(&password, &[])?;
+        Ok((report.score >= min_score).then_some((password, report)))
+    })?
+    .ok_or_else(|| {
+        anyhow::anyhow!(
+            "Failed to generate a password with score >= {} after {} attempts, try a longer length",
+            min_score,
+            MAX_ATTEMPTS
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_strength() -> Result<()> {
+        let report = process_password_strength("password", &[])?;
+        assert_eq!(report.score, 0);
+        assert!(report.warning.is_some());
+
+        let report = process_password_strength("kaka2024", &["kaka"])?;
+        assert!(report.score < 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_min_score() -> Result<()> {
+        let mut candidates = vec!["V9#qk_Lm2@xzP7!r", "password", "123456"];
+        let (password, report) =
+            process_genpass_min_score(4, || Ok(candidates.pop().unwrap_or_default().into()))?;
+        assert_eq!(password, "V9#qk_Lm2@xzP7!r");
+        assert_eq!(report.score, 4);
+
+        assert!(process_genpass_min_score(4, || Ok("password".into())).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_password_audit() -> Result<()> {
+        let mut reader = "password\n\nacme-kaka\nV9#qk_Lm2@xzP7!r\n".as_bytes();
+        let mut corpus = BreachCorpus::open("fixtures/pwned_sample.txt")?;
+        let report = process_password_audit(&mut reader, &["acme", "kaka"], Some(&mut corpus))?;
+        let lines: Vec<usize> = report.entries.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![1, 3, 4]);
+        assert_eq!(report.histogram.iter().sum::<usize>(), 3);
+        assert_eq!(report.count_below(3), 2);
+        assert_eq!(report.entries[0].breached, Some(9659365));
+        assert_eq!(report.count_breached(), 1);
+        Ok(())
+    }
+}
@@ -1,15 +1,19 @@
 use std::{collections::HashMap, io::Read};
 
 use anyhow::Result;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Utc};
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
-use rand::rngs::OsRng;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
 
+use aes_gcm::Aes256Gcm;
 use chacha20poly1305::{
-    aead::{Aead, KeyInit},
-    ChaCha20Poly1305, Nonce,
+    aead::{self, Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Nonce, XChaCha20Poly1305,
 };
 
-use crate::{process_genpass, TextSignFormat};
+use crate::{Argon2Params, TextCipher, TextSignFormat};
 
 pub trait TextSign {
     /// Sign the data from the reader and return the signature
@@ -26,6 +30,33 @@ pub trait TextEncrypt {
     fn encrypt(&self, reader: &mut dyn Read) -> Result<Vec<u8>>;
 }
 
+/// Encrypted messages start with `ENCRYPT_MAGIC`, the format version, the cipher and how the key
+/// was obtained, followed by the nonce
+const ENCRYPT_MAGIC: &[u8; 3] = b"RCE";
+const ENCRYPT_VERSION: u8 = 1;
+const LEGACY_NONCE_LEN: usize = 12;
+
+/// The key is read from a key file
+const KDF_KEY: u8 = 0;
+/// The key is derived from a password, the Argon2id parameters and the salt follow in the header
+const KDF_ARGON2ID: u8 = 1;
+const SALT_LEN: usize = 16;
+const KEY_FILE_VERSION: u8 = 1;
+/// One 32 byte key file is shared by all the AEAD ciphers, the cipher is picked per message
+const AEAD_KEY: &str = "aead";
+const ED25519_SECRET: &str = "ed25519-secret";
+const ED25519_PUBLIC: &str = "ed25519-public";
+
+/// Key file written by `text generate`, older versions wrote the raw key instead
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyFile {
+    version: u8,
+    algorithm: String,
+    created: DateTime<Utc>,
+    /// base64url without padding
+    key: String,
+}
+
 pub trait TextDecrypt {
     /// Decrypt the data from the reader and return the plaintext
     fn decrypt(&self, buf: &[u8]) -> Result<Vec<u8>>;
@@ -39,9 +70,37 @@ struct Ed25519Signer {
     key: SigningKey,
 }
 
-struct EncryptChaCha20 {
+/// Cipher and key derivation of a message, read from its header
+struct Header {
+    cipher: TextCipher,
+    /// Argon2id parameters and salt of password messages
+    password: Option<(Argon2Params, Vec<u8>)>,
+    /// Length of the header in the message, the nonce follows
+    len: usize,
+}
+
+struct EncryptWithKey {
+    cipher: TextCipher,
+    key: [u8; 32],
+}
+
+/// The cipher is read from the message header, so only the key is needed
+struct DecryptWithKey {
     key: [u8; 32],
-    nonce: [u8; 12],
+    /// 旧版本每条消息都用 key 文件里固定的 nonce，只在解密旧消息时用
+    legacy_nonce: Option<[u8; LEGACY_NONCE_LEN]>,
+}
+
+/// Key derived from a password with Argon2id, with a new random salt per message
+struct EncryptWithPassword {
+    cipher: TextCipher,
+    password: Vec<u8>,
+    params: Argon2Params,
+}
+
+/// The cipher, the Argon2id parameters and the salt are read from the message header
+struct DecryptWithPassword {
+    password: Vec<u8>,
 }
 
 struct Ed25519Verifier {
@@ -99,55 +158,264 @@ impl TextVerifier for Ed25519Verifier {
     }
 }
 
-impl TextEncrypt for EncryptChaCha20 {
+/// Output: the header, a random nonce per message, then the ciphertext
+///
+/// The header is authenticated as associated data, so it can't be changed without failing decryption
+impl TextEncrypt for EncryptWithKey {
     fn encrypt(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
-        let cipher = ChaCha20Poly1305::new_from_slice(&self.key)
-            .map_err(|_| anyhow::anyhow!("Failed to create ChaChaPoly1305 instance"))?;
-        let nonce = Nonce::from_slice(&self.nonce);
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+
+        let header = Header::encode(self.cipher, None);
+        self.cipher.seal(&self.key, header, &buf)
+    }
+}
+
+impl TextDecrypt for DecryptWithKey {
+    fn decrypt(&self, buf: &[u8]) -> Result<Vec<u8>> {
+        if let Some(nonce) = &self.legacy_nonce {
+            let cipher = ChaCha20Poly1305::new_from_slice(&self.key)
+                .map_err(|_| anyhow::anyhow!("Failed to create ChaChaPoly1305 instance"))?;
+            return cipher
+                .decrypt(Nonce::from_slice(nonce), buf)
+                .map_err(|_| anyhow::anyhow!("Failed to decrypt data"));
+        }
+
+        let header = Header::parse(buf)?;
+        if header.password.is_some() {
+            anyhow::bail!("Message is encrypted with a password, use --password");
+        }
+        header.cipher.open(&self.key, buf, header.len)
+    }
+}
 
+/// The header also holds the Argon2id memory/iterations/parallelism and the salt
+///
+/// Decryption only needs the password, everything else is read from the header
+impl TextEncrypt for EncryptWithPassword {
+    fn encrypt(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf)?;
 
-        let ciphertext = cipher
-            .encrypt(nonce, &buf[..])
-            .map_err(|_| anyhow::anyhow!("Failed to encrypt data"))?;
-        Ok(ciphertext)
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let key = self.params.argon2id(&self.password, &salt)?;
+
+        let header = Header::encode(self.cipher, Some((&self.params, &salt)));
+        self.cipher.seal(&key, header, &buf)
     }
 }
 
-impl TextDecrypt for EncryptChaCha20 {
+impl TextDecrypt for DecryptWithPassword {
     fn decrypt(&self, buf: &[u8]) -> Result<Vec<u8>> {
-        let cipher = ChaCha20Poly1305::new_from_slice(&self.key)
-            .map_err(|_| anyhow::anyhow!("Failed to create ChaChaPoly1305 instance"))?;
-        let nonce = Nonce::from_slice(&self.nonce);
+        let header = Header::parse(buf)?;
+        let Some((params, salt)) = &header.password else {
+            anyhow::bail!("Message is encrypted with a key file, use --key");
+        };
+
+        let key = params.argon2id(&self.password, salt)?;
+        header
+            .cipher
+            .open(&key, buf, header.len)
+            .map_err(|_| anyhow::anyhow!("Failed to decrypt data, wrong password?"))
+    }
+}
 
-        let plaintext = cipher
-            .decrypt(nonce, buf)
-            .map_err(|_| anyhow::anyhow!("Failed to decrypt data"))?;
-        Ok(plaintext)
+impl Header {
+    /// magic | version | cipher | kdf, then for passwords memory/iterations/parallelism
+    /// (u32 big endian) and the salt
+    fn encode(cipher: TextCipher, password: Option<(&Argon2Params, &[u8])>) -> Vec<u8> {
+        let mut header = ENCRYPT_MAGIC.to_vec();
+        header.push(ENCRYPT_VERSION);
+        header.push(cipher.id());
+        match password {
+            Some((params, salt)) => {
+                header.push(KDF_ARGON2ID);
+                for value in [params.memory, params.iterations, params.parallelism] {
+                    header.extend_from_slice(&value.to_be_bytes());
+                }
+                header.extend_from_slice(salt);
+            }
+            None => header.push(KDF_KEY),
+        }
+        header
     }
+
+    fn parse(buf: &[u8]) -> Result<Self> {
+        if !buf.starts_with(ENCRYPT_MAGIC) {
+            anyhow::bail!(
+                "Not an encrypted message, use --legacy for messages from older versions"
+            );
+        }
+        let truncated = || anyhow::anyhow!("Truncated message");
+        let mut len = ENCRYPT_MAGIC.len() + 3;
+        let [version, cipher, kdf]: [u8; 3] = buf
+            .get(ENCRYPT_MAGIC.len()..len)
+            .ok_or_else(truncated)?
+            .try_into()?;
+        if version != ENCRYPT_VERSION {
+            anyhow::bail!("Unsupported message version {}", version);
+        }
+        let cipher = TextCipher::from_id(cipher)?;
+
+        let password = match kdf {
+            KDF_KEY => None,
+            KDF_ARGON2ID => {
+                let fields = buf.get(len..len + 3 * 4 + SALT_LEN).ok_or_else(truncated)?;
+                len += fields.len();
+                let (params, salt) = fields.split_at(3 * 4);
+                let value =
+                    |i: usize| u32::from_be_bytes(params[i * 4..i * 4 + 4].try_into().unwrap());
+                let params = Argon2Params {
+                    memory: value(0),
+                    iterations: value(1),
+                    parallelism: value(2),
+                };
+                // 参数是从消息里读出来的，有上限，免得构造出来的消息耗光内存或者一直算下去
+                params
+                    .check()
+                    .map_err(|e| anyhow::anyhow!("Invalid message: {}", e))?;
+                Some((params, salt.to_vec()))
+            }
+            kdf => anyhow::bail!("Unsupported key derivation {}", kdf),
+        };
+        Ok(Self {
+            cipher,
+            password,
+            len,
+        })
+    }
+}
+
+impl TextCipher {
+    /// Byte identifying the cipher in the message header
+    fn id(&self) -> u8 {
+        match self {
+            TextCipher::ChaCha20 => 1,
+            TextCipher::XChaCha20 => 2,
+            TextCipher::Aes256Gcm => 3,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self> {
+        match id {
+            1 => Ok(TextCipher::ChaCha20),
+            2 => Ok(TextCipher::XChaCha20),
+            3 => Ok(TextCipher::Aes256Gcm),
+            _ => anyhow::bail!("Unsupported cipher {}", id),
+        }
+    }
+
+    fn seal(&self, key: &[u8; 32], header: Vec<u8>, plaintext: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            TextCipher::ChaCha20 => seal::<ChaCha20Poly1305>(key, header, plaintext),
+            TextCipher::XChaCha20 => seal::<XChaCha20Poly1305>(key, header, plaintext),
+            TextCipher::Aes256Gcm => seal::<Aes256Gcm>(key, header, plaintext),
+        }
+    }
+
+    fn open(&self, key: &[u8; 32], buf: &[u8], header_len: usize) -> Result<Vec<u8>> {
+        match self {
+            TextCipher::ChaCha20 => open::<ChaCha20Poly1305>(key, buf, header_len),
+            TextCipher::XChaCha20 => open::<XChaCha20Poly1305>(key, buf, header_len),
+            TextCipher::Aes256Gcm => open::<Aes256Gcm>(key, buf, header_len),
+        }
+    }
+}
+
+/// Encrypt `plaintext` with a random nonce and `header` as associated data,
+/// returns the header, the nonce and the ciphertext
+fn seal<C: Aead + KeyInit>(key: &[u8; 32], header: Vec<u8>, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let cipher =
+        C::new_from_slice(key).map_err(|_| anyhow::anyhow!("Failed to create cipher instance"))?;
+    // 同一个 key 下 nonce 重复会泄露明文的异或并且可以伪造消息，所以每条消息都随机生成
+    // 96 位的 nonce 随机生成时同一个 key 最好不要超过 2^32 条消息，XChaCha20 的 192 位就不用担心
+    let mut nonce = aead::Nonce::<C>::default();
+    OsRng.fill_bytes(&mut nonce);
+
+    let payload = Payload {
+        msg: plaintext,
+        aad: &header,
+    };
+    let ciphertext = cipher
+        .encrypt(&nonce, payload)
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt data"))?;
+
+    let mut ret = header;
+    ret.extend_from_slice(&nonce);
+    ret.extend_from_slice(&ciphertext);
+    Ok(ret)
+}
+
+/// Reverse of `seal`, the first `header_len` bytes of `buf` are the header
+fn open<C: Aead + KeyInit>(key: &[u8; 32], buf: &[u8], header_len: usize) -> Result<Vec<u8>> {
+    let cipher =
+        C::new_from_slice(key).map_err(|_| anyhow::anyhow!("Failed to create cipher instance"))?;
+    let nonce_len = aead::Nonce::<C>::default().len();
+    if buf.len() < header_len + nonce_len {
+        anyhow::bail!("Truncated message");
+    }
+    let (header, rest) = buf.split_at(header_len);
+    let (nonce, ciphertext) = rest.split_at(nonce_len);
+
+    let payload = Payload {
+        msg: ciphertext,
+        aad: header,
+    };
+    cipher
+        .decrypt(aead::Nonce::<C>::from_slice(nonce), payload)
+        .map_err(|_| anyhow::anyhow!("Failed to decrypt data"))
 }
 
-impl EncryptChaCha20 {
-    pub fn new(key: [u8; 32], nonce: [u8; 12]) -> Self {
-        Self { key, nonce }
+impl EncryptWithKey {
+    pub fn new(cipher: TextCipher, key: [u8; 32]) -> Self {
+        Self { cipher, key }
     }
 
-    pub fn try_new(key_and_nonce: impl AsRef<[u8]>) -> Result<Self> {
-        let key_and_nonce = key_and_nonce.as_ref();
-        let key = (&key_and_nonce[..32]).try_into()?;
-        let nonce = (&key_and_nonce[32..32 + 12]).try_into()?;
-        Ok(Self::new(key, nonce))
+    /// Old key files have the fixed nonce after the key, it is ignored here
+    pub fn try_new(cipher: TextCipher, key_file: impl AsRef<[u8]>) -> Result<Self> {
+        Ok(Self::new(cipher, aead_key(key_file.as_ref())?))
     }
 
+    /// The key works with every cipher, so the file is tagged with the generic AEAD algorithm
     fn generate() -> Result<HashMap<&'static str, Vec<u8>>> {
-        let key = process_genpass(48, true, true, true, true)?;
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
         let mut map = HashMap::new();
-        map.insert("chacha20.txt", key.as_bytes().to_vec());
+        map.insert("aead.txt", KeyFile::encode(AEAD_KEY, &key)?);
         Ok(map)
     }
 }
 
+impl DecryptWithKey {
+    pub fn new(key: [u8; 32], legacy_nonce: Option<[u8; LEGACY_NONCE_LEN]>) -> Self {
+        Self { key, legacy_nonce }
+    }
+
+    /// The key file holds the key, old key files also have the fixed nonce after it
+    pub fn try_new(key_file: impl AsRef<[u8]>, legacy: bool) -> Result<Self> {
+        let key_file = key_file.as_ref();
+        let legacy_nonce = match legacy {
+            true => Some(
+                key_file
+                    .get(32..32 + LEGACY_NONCE_LEN)
+                    .ok_or_else(|| anyhow::anyhow!("Key file has no nonce for --legacy"))?
+                    .try_into()?,
+            ),
+            false => None,
+        };
+        Ok(Self::new(aead_key(key_file)?, legacy_nonce))
+    }
+}
+
+/// The first 32 bytes of the key file content
+fn aead_key(key_file: &[u8]) -> Result<[u8; 32]> {
+    let Some(key) = key_file.get(..32) else {
+        anyhow::bail!("Key must be at least 32 bytes");
+    };
+    Ok(key.try_into()?)
+}
+
 impl Blake3 {
     pub fn new(key: [u8; 32]) -> Self {
         Self { key }
@@ -164,9 +432,10 @@ impl Blake3 {
     }
 
     fn generate() -> Result<HashMap<&'static str, Vec<u8>>> {
-        let key = process_genpass(32, true, true, true, true)?;
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
         let mut map = HashMap::new();
-        map.insert("blake3.txt", key.as_bytes().to_vec());
+        map.insert("blake3.txt", KeyFile::encode("blake3", &key)?);
         Ok(map)
     }
 }
@@ -179,8 +448,10 @@ impl Ed25519Signer {
 
     pub fn try_new(key: impl AsRef<[u8]>) -> Result<Self> {
         let key = key.as_ref();
-        let key = (&key[..32]).try_into()?;
-        Ok(Self::new(key))
+        let Some(key) = key.get(..32) else {
+            anyhow::bail!("Ed25519 secret key must be 32 bytes");
+        };
+        Ok(Self::new(key.try_into()?))
     }
 
     fn generate() -> Result<HashMap<&'static str, Vec<u8>>> {
@@ -188,8 +459,14 @@ impl Ed25519Signer {
         let sk: SigningKey = SigningKey::generate(&mut csprng);
         let pk: VerifyingKey = sk.verifying_key();
         let mut map = HashMap::new();
-        map.insert("ed25519_sk.txt", sk.to_bytes().to_vec());
-        map.insert("ed25519_pk.txt", pk.to_bytes().to_vec());
+        map.insert(
+            "ed25519_sk.txt",
+            KeyFile::encode(ED25519_SECRET, &sk.to_bytes())?,
+        );
+        map.insert(
+            "ed25519_pk.txt",
+            KeyFile::encode(ED25519_PUBLIC, &pk.to_bytes())?,
+        );
 
         Ok(map)
     }
@@ -198,32 +475,97 @@ impl Ed25519Signer {
 impl Ed25519Verifier {
     pub fn try_new(key: impl AsRef<[u8]>) -> Result<Self> {
         let key = key.as_ref();
-        let key = (&key[..32]).try_into()?;
-        let key = VerifyingKey::from_bytes(key)?;
+        let Some(key) = key.get(..32) else {
+            anyhow::bail!("Ed25519 public key must be 32 bytes");
+        };
+        let key = VerifyingKey::from_bytes(key.try_into()?)?;
         Ok(Self { key })
     }
 }
 
+impl KeyFile {
+    fn encode(algorithm: &str, key: &[u8]) -> Result<Vec<u8>> {
+        let file = KeyFile {
+            version: KEY_FILE_VERSION,
+            algorithm: algorithm.to_string(),
+            created: Utc::now(),
+            key: URL_SAFE_NO_PAD.encode(key),
+        };
+        let mut ret = serde_json::to_vec_pretty(&file)?;
+        ret.push(b'\n');
+        Ok(ret)
+    }
+}
+
+/// Raw key from the content of a key file, which must be a key for `algorithm`
+///
+/// Key files from older versions (e.g. the fixtures) hold the raw key and are returned as is
+fn load_key(content: &[u8], algorithm: &str) -> Result<Vec<u8>> {
+    // 旧的 key 文件是 genpass 生成的文本或者原始字节，不会是以 "{" 开头的 UTF-8
+    let is_key_file = content.starts_with(b"{") && std::str::from_utf8(content).is_ok();
+    if !is_key_file {
+        return Ok(content.to_vec());
+    }
+
+    let file: KeyFile =
+        serde_json::from_slice(content).map_err(|e| anyhow::anyhow!("Invalid key file: {}", e))?;
+    if file.version != KEY_FILE_VERSION {
+        anyhow::bail!("Unsupported key file version {}", file.version);
+    }
+    if file.algorithm != algorithm {
+        anyhow::bail!(
+            "Expected a key for {}, got a key for {}",
+            algorithm,
+            file.algorithm
+        );
+    }
+    Ok(URL_SAFE_NO_PAD.decode(file.key.trim())?)
+}
+
+/// The key file from `text generate --format chacha20` works with every cipher
 pub fn process_text_encrypt(
     reader: &mut dyn Read,
     key: &[u8],
-    format: TextSignFormat,
+    cipher: TextCipher,
 ) -> Result<Vec<u8>> {
-    let encrypt: Box<dyn TextEncrypt> = match format {
-        TextSignFormat::ChaCha20 => Box::new(EncryptChaCha20::try_new(key)?),
-        _ => anyhow::bail!("Unsupported format"),
-    };
+    let key = load_key(key, AEAD_KEY)?;
+    let encrypt: Box<dyn TextEncrypt> = Box::new(EncryptWithKey::try_new(cipher, key)?);
 
     encrypt.encrypt(reader)
 }
 
-pub fn process_text_decrypt(reader: &[u8], key: &[u8], format: TextSignFormat) -> Result<Vec<u8>> {
-    let decrypt: Box<dyn TextDecrypt> = match format {
-        TextSignFormat::ChaCha20 => Box::new(EncryptChaCha20::try_new(key)?),
-        _ => anyhow::bail!("Unsupported format"),
-    };
+/// The cipher is read from the message header
+///
+/// With `legacy`, decrypt a headerless ChaCha20 message encrypted with the fixed nonce from the key file
+pub fn process_text_decrypt(buf: &[u8], key: &[u8], legacy: bool) -> Result<Vec<u8>> {
+    let key = load_key(key, AEAD_KEY)?;
+    let decrypt: Box<dyn TextDecrypt> = Box::new(DecryptWithKey::try_new(key, legacy)?);
 
-    decrypt.decrypt(reader)
+    decrypt.decrypt(buf)
+}
+
+/// Encrypt with a key derived from `password`, the salt and `params` are stored in the message
+pub fn process_text_encrypt_password(
+    reader: &mut dyn Read,
+    password: &[u8],
+    params: Argon2Params,
+    cipher: TextCipher,
+) -> Result<Vec<u8>> {
+    let encrypt: Box<dyn TextEncrypt> = Box::new(EncryptWithPassword {
+        cipher,
+        password: password.to_vec(),
+        params,
+    });
+
+    encrypt.encrypt(reader)
+}
+
+pub fn process_text_decrypt_password(buf: &[u8], password: &[u8]) -> Result<Vec<u8>> {
+    let decrypt: Box<dyn TextDecrypt> = Box::new(DecryptWithPassword {
+        password: password.to_vec(),
+    });
+
+    decrypt.decrypt(buf)
 }
 
 pub fn process_text_sign(
@@ -232,8 +574,10 @@ pub fn process_text_sign(
     format: TextSignFormat,
 ) -> Result<Vec<u8>> {
     let signer: Box<dyn TextSign> = match format {
-        TextSignFormat::Blake3 => Box::new(Blake3::try_new(key)?),
-        TextSignFormat::Ed25519 => Box::new(Ed25519Signer::try_new(key)?),
+        TextSignFormat::Blake3 => Box::new(Blake3::try_new(load_key(key, "blake3")?)?),
+        TextSignFormat::Ed25519 => {
+            Box::new(Ed25519Signer::try_new(load_key(key, ED25519_SECRET)?)?)
+        }
         _ => anyhow::bail!("Unsupported format"),
     };
 
@@ -247,8 +591,10 @@ pub fn process_text_verify(
     format: TextSignFormat,
 ) -> Result<bool> {
     let verifier: Box<dyn TextVerifier> = match format {
-        TextSignFormat::Blake3 => Box::new(Blake3::try_new(key)?),
-        TextSignFormat::Ed25519 => Box::new(Ed25519Verifier::try_new(key)?),
+        TextSignFormat::Blake3 => Box::new(Blake3::try_new(load_key(key, "blake3")?)?),
+        TextSignFormat::Ed25519 => {
+            Box::new(Ed25519Verifier::try_new(load_key(key, ED25519_PUBLIC)?)?)
+        }
         _ => anyhow::bail!("Unsupported format"),
     };
 
@@ -259,18 +605,20 @@ pub fn process_text_key_generate(format: TextSignFormat) -> Result<HashMap<&'sta
     match format {
         TextSignFormat::Blake3 => Blake3::generate(),
         TextSignFormat::Ed25519 => Ed25519Signer::generate(),
-        TextSignFormat::ChaCha20 => EncryptChaCha20::generate(),
+        TextSignFormat::ChaCha20 => EncryptWithKey::generate(),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{MAX_ARGON2_ITERATIONS, MAX_ARGON2_PARALLELISM};
     use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 
     const BLAKE3_KEY: &[u8] = include_bytes!("../../fixtures/blake3.txt");
     const ED25519_SK: &[u8] = include_bytes!("../../fixtures/ed25519_sk.txt");
     const ED25519_PK: &[u8] = include_bytes!("../../fixtures/ed25519_pk.txt");
+    const CHACHA20_KEY: &[u8] = include_bytes!("../../fixtures/chacha20.txt");
 
     #[test]
     fn test_blake3_sign_verify() -> Result<()> {
@@ -297,4 +645,131 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_encrypt_decrypt() -> Result<()> {
+        for cipher in [
+            TextCipher::ChaCha20,
+            TextCipher::XChaCha20,
+            TextCipher::Aes256Gcm,
+        ] {
+            let a = process_text_encrypt(&mut "hello".as_bytes(), CHACHA20_KEY, cipher)?;
+            let b = process_text_encrypt(&mut "hello".as_bytes(), CHACHA20_KEY, cipher)?;
+            assert_eq!(a[4], cipher.id());
+            // 每条消息的 nonce 都不一样
+            assert_ne!(a[6..18], b[6..18]);
+            assert_eq!(process_text_decrypt(&a, CHACHA20_KEY, false)?, b"hello");
+
+            // header 是认证过的，改成别的 cipher 也解不开
+            let mut tampered = a.clone();
+            tampered[4] = cipher.id() % 3 + 1;
+            assert!(process_text_decrypt(&tampered, CHACHA20_KEY, false).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_chacha20_legacy_decrypt() -> Result<()> {
+        // 旧格式：没有 header，用 key 文件里第 32..44 字节作为固定 nonce
+        let cipher = ChaCha20Poly1305::new_from_slice(&CHACHA20_KEY[..32])?;
+        let nonce = Nonce::from_slice(&CHACHA20_KEY[32..44]);
+        let legacy = cipher.encrypt(nonce, &b"hello"[..]).unwrap();
+
+        assert_eq!(process_text_decrypt(&legacy, CHACHA20_KEY, true)?, b"hello");
+        assert!(process_text_decrypt(&legacy, CHACHA20_KEY, false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_password_encrypt_decrypt() -> Result<()> {
+        let params = Argon2Params {
+            memory: 64,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let encrypted = process_text_encrypt_password(
+            &mut "hello".as_bytes(),
+            b"secret",
+            params,
+            TextCipher::XChaCha20,
+        )?;
+        assert_eq!(encrypted[5], KDF_ARGON2ID);
+        assert_eq!(
+            process_text_decrypt_password(&encrypted, b"secret")?,
+            b"hello"
+        );
+        assert!(process_text_decrypt_password(&encrypted, b"wrong").is_err());
+        // 参数也在认证范围内，改了就解不开
+        let mut tampered = encrypted.clone();
+        tampered[13] = 2;
+        assert!(process_text_decrypt_password(&tampered, b"secret").is_err());
+        let mut tampered = encrypted.clone();
+        tampered[14] = 0xff;
+        let err = process_text_decrypt_password(&tampered, b"secret").unwrap_err();
+        assert!(err.to_string().starts_with("Invalid message"));
+        // key 和 password 两种消息不能混用
+        assert!(process_text_decrypt(&encrypted, CHACHA20_KEY, false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_password_encrypt_rejects_large_params() {
+        let params = Argon2Params {
+            memory: 64,
+            iterations: MAX_ARGON2_ITERATIONS + 1,
+            parallelism: 1,
+        };
+        let encrypt = |params| {
+            process_text_encrypt_password(
+                &mut "hello".as_bytes(),
+                b"secret",
+                params,
+                TextCipher::ChaCha20,
+            )
+        };
+        assert!(encrypt(params).is_err());
+        assert!(encrypt(Argon2Params {
+            iterations: 1,
+            parallelism: MAX_ARGON2_PARALLELISM + 1,
+            ..params
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_generated_key_files() -> Result<()> {
+        let keys = process_text_key_generate(TextSignFormat::Ed25519)?;
+        let (sk, pk) = (&keys["ed25519_sk.txt"], &keys["ed25519_pk.txt"]);
+        let format = TextSignFormat::Ed25519;
+        let sig = process_text_sign(&mut "hello".as_bytes(), sk, format)?;
+        assert!(process_text_verify(
+            &mut "hello".as_bytes(),
+            pk,
+            &sig,
+            format
+        )?);
+        // 公钥文件不能当私钥用
+        assert!(process_text_sign(&mut "hello".as_bytes(), pk, format).is_err());
+
+        let keys = process_text_key_generate(TextSignFormat::ChaCha20)?;
+        let key = &keys["aead.txt"];
+        let file: KeyFile = serde_json::from_slice(key)?;
+        assert_eq!(file.algorithm, AEAD_KEY);
+        assert!(load_key(key, "blake3").is_err());
+        // 同一个 key 文件三种算法都能用
+        for cipher in [
+            TextCipher::ChaCha20,
+            TextCipher::XChaCha20,
+            TextCipher::Aes256Gcm,
+        ] {
+            let encrypted = process_text_encrypt(&mut "hello".as_bytes(), key, cipher)?;
+            assert_eq!(process_text_decrypt(&encrypted, key, false)?, b"hello");
+        }
+        // 签名用的 key 文件不能拿来加密
+        let blake3 = &process_text_key_generate(TextSignFormat::Blake3)?["blake3.txt"];
+        assert!(
+            process_text_encrypt(&mut "hello".as_bytes(), blake3, TextCipher::ChaCha20).is_err()
+        );
+        Ok(())
+    }
 }
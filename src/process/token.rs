@@ -0,0 +1,153 @@
+use anyhow::Result;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand::{rngs::OsRng, RngCore};
+
+use crate::TokenEncoding;
+
+const BASE62: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+impl TokenEncoding {
+    fn encode(&self, data: &[u8]) -> String {
+        match self {
+            TokenEncoding::Base62 => base62_encode(data),
+            TokenEncoding::Hex => data.iter().map(|b| format!("{:02x}", b)).collect(),
+            TokenEncoding::Base64Url => URL_SAFE_NO_PAD.encode(data),
+        }
+    }
+
+    /// Length of the encoded 4-byte CRC32 checksum at the end of the token
+    fn checksum_len(&self) -> usize {
+        match self {
+            TokenEncoding::Base62 => 6,
+            TokenEncoding::Hex => 8,
+            TokenEncoding::Base64Url => 6,
+        }
+    }
+
+    fn is_valid_char(&self, c: u8) -> bool {
+        match self {
+            TokenEncoding::Base62 => c.is_ascii_alphanumeric(),
+            TokenEncoding::Hex => c.is_ascii_digit() || (b'a'..=b'f').contains(&c),
+            TokenEncoding::Base64Url => c.is_ascii_alphanumeric() || c == b'-' || c == b'_',
+        }
+    }
+}
+
+/// Encode as a fixed width big-endian base62 number, so tokens of the same size have the same length
+fn base62_encode(data: &[u8]) -> String {
+    let width = (data.len() as f64 * 8.0 / 62f64.log2()).ceil() as usize;
+    let mut num = data.to_vec();
+    let mut digits = Vec::with_capacity(width);
+    while digits.len() < width {
+        // 大数除以 62，余数就是最低位
+        let mut rem = 0u32;
+        for byte in num.iter_mut() {
+            let cur = (rem << 8) | *byte as u32;
+            *byte = (cur / 62) as u8;
+            rem = cur % 62;
+        }
+        digits.push(BASE62[rem as usize]);
+    }
+    digits.reverse();
+    String::from_utf8(digits).expect("Base62 digits are ascii")
+}
+
+fn checksum(body: &str, encoding: TokenEncoding) -> String {
+    let crc = crc32fast::hash(body.as_bytes());
+    encoding.encode(&crc.to_be_bytes())
+}
+
+/// Generate a random token: prefix + encoded random bytes + encoded CRC32 of everything before it
+pub fn process_token_generate(
+    prefix: &str,
+    bytes: usize,
+    encoding: TokenEncoding,
+) -> Result<String> {
+    if bytes < 16 {
+        anyhow::bail!("Token needs at least 16 random bytes");
+    }
+
+    let mut buf = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buf);
+    let body = format!("{}{}", prefix, encoding.encode(&buf));
+    let checksum = checksum(&body, encoding);
+    Ok(body + &checksum)
+}
+
+/// Check the token's embedded checksum offline, and its prefix if one is given
+///
+/// Without `prefix`, everything up to the last `_` is treated as the prefix
+pub fn process_token_verify(
+    token: &str,
+    prefix: Option<&str>,
+    encoding: TokenEncoding,
+) -> Result<bool> {
+    let token = token.trim();
+    if let Some(prefix) = prefix {
+        if !token.starts_with(prefix) {
+            return Ok(false);
+        }
+    }
+
+    let checksum_len = encoding.checksum_len();
+    if !token.is_ascii() || token.len() <= checksum_len {
+        return Ok(false);
+    }
+    let (body, sum) = token.split_at(token.len() - checksum_len);
+    let payload = match prefix {
+        Some(prefix) => match body.get(prefix.len()..) {
+            Some(payload) => payload,
+            None => return Ok(false),
+        },
+        // base64url 的随机部分里也可能有 "_"，这时只检查最后一段，反正整个 body 都在 checksum 里
+        None => body.rsplit('_').next().unwrap_or(body),
+    };
+    if !sum
+        .bytes()
+        .chain(payload.bytes())
+        .all(|c| encoding.is_valid_char(c))
+    {
+        return Ok(false);
+    }
+
+    Ok(checksum(body, encoding) == sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_generate_verify() -> Result<()> {
+        for encoding in [
+            TokenEncoding::Base62,
+            TokenEncoding::Hex,
+            TokenEncoding::Base64Url,
+        ] {
+            let token = process_token_generate("rcli_live_", 32, encoding)?;
+            assert!(token.starts_with("rcli_live_"));
+            assert!(process_token_verify(&token, Some("rcli_live_"), encoding)?);
+            // 不给 prefix 时按最后一个 "_" 拆开
+            assert!(process_token_verify(&token, None, encoding)?);
+            assert!(!process_token_verify(&token, Some("rcli_test_"), encoding)?);
+
+            // 改掉随机部分的一个字符，checksum 就对不上了
+            let mut tampered = token.into_bytes();
+            tampered[12] = if tampered[12] == b'a' { b'b' } else { b'a' };
+            let tampered = String::from_utf8(tampered)?;
+            assert!(!process_token_verify(&tampered, None, encoding)?);
+        }
+        assert!(!process_token_verify(
+            "rcli_live_abc",
+            Some("rcli_live_"),
+            TokenEncoding::Base62
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_base62_encode() {
+        assert_eq!(base62_encode(&[0, 0, 0, 61]), "00000z");
+        assert_eq!(base62_encode(&[0xff; 4]), "4gfFC3");
+    }
+}
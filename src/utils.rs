@@ -1,5 +1,8 @@
 use anyhow::Result;
-use std::{fs::File, io::Read};
+use std::{
+    fs::File,
+    io::{Read, Write},
+};
 
 pub fn get_reader(input: &str) -> Result<Box<dyn Read>> {
     let reader: Box<dyn Read> = if input == "-" {
@@ -10,9 +13,74 @@ pub fn get_reader(input: &str) -> Result<Box<dyn Read>> {
     Ok(reader)
 }
 
+pub fn get_writer(output: &str) -> Result<Box<dyn Write>> {
+    let writer: Box<dyn Write> = if output == "-" {
+        Box::new(std::io::stdout())
+    } else {
+        Box::new(File::create(output)?)
+    };
+    Ok(writer)
+}
+
 pub fn get_content(input: &str) -> Result<Vec<u8>> {
     let mut reader = get_reader(input)?;
     let mut buf = Vec::new();
     reader.read_to_end(&mut buf)?;
     Ok(buf)
 }
+
+/// Content of a secret file without the trailing line breaks, so `echo xxx > file` matches typing it at a prompt
+pub fn get_secret(input: &str) -> Result<Vec<u8>> {
+    let mut content = get_content(input)?;
+    let len = content
+        .iter()
+        .rposition(|c| !matches!(c, b'\n' | b'\r'))
+        .map_or(0, |i| i + 1);
+    content.truncate(len);
+    Ok(content)
+}
+
+/// `hexdump -C` style view of at most `limit` bytes of `data`
+pub fn hexdump(data: &[u8], limit: usize) -> String {
+    let mut ret = String::new();
+    for (i, chunk) in data[..data.len().min(limit)].chunks(16).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+        let (left, right) = hex.split_at(hex.len().min(8));
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        ret.push_str(&format!(
+            "{:08x}  {:<23}  {:<23}  |{}|\n",
+            i * 16,
+            left.join(" "),
+            right.join(" "),
+            ascii
+        ));
+    }
+    if data.len() > limit {
+        ret.push_str(&format!("... {} more bytes\n", data.len() - limit));
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hexdump() {
+        let dump = hexdump(b"hello, world\x00\x01\xff rust", 16);
+        assert_eq!(
+            dump,
+            "00000000  68 65 6c 6c 6f 2c 20 77  6f 72 6c 64 00 01 ff 20  |hello, world... |\n\
+             ... 4 more bytes\n"
+        );
+    }
+}